@@ -0,0 +1,486 @@
+use crate::cache;
+use crate::checksum::{Algorithm, Checksum};
+use crate::maven::{
+    self, Download, DownloadRequest, MavenMetadata, Project, SearchResult, Versioning, Versions,
+};
+use crate::signature::{SignatureError, Verifier};
+use crate::version;
+use anyhow::{Context, Result};
+use indicatif::MultiProgress;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Search results are capped like search.maven.org's
+const MAX_SEARCH_RESULTS: usize = 20;
+
+/// Where POMs, version lists and jars come from. `MavenClient` fetches them
+/// from Maven repositories over HTTP; `DirectoryBackend` reads a directory laid
+/// out like one, and tests use `MemoryBackend`.
+pub trait RepositoryBackend {
+    /// The published versions of an artifact
+    fn get_metadata(&self, group_id: &str, artifact_id: &str) -> Result<MavenMetadata>;
+
+    fn get_pom(&self, group_id: &str, artifact_id: &str, version: &str) -> Result<Project>;
+
+    /// POMs of several `(group, artifact, version)`s, in order
+    fn get_poms(&self, coordinates: &[(String, String, String)]) -> Vec<Result<Project>> {
+        coordinates
+            .iter()
+            .map(|(group_id, artifact_id, version)| self.get_pom(group_id, artifact_id, version))
+            .collect()
+    }
+
+    /// Download files to their `output_path`, each only once it passed the
+    /// backend's checks. Results are in the order of `requests`.
+    fn download_all(
+        &self,
+        requests: &[DownloadRequest],
+        progress: &MultiProgress,
+    ) -> Vec<Result<Download>>;
+
+    fn search_artifact(&self, query: &str) -> Result<Vec<SearchResult>>;
+
+    /// Name of the repository that served `coordinate`, to record in jpkg.lock
+    fn origin(&self, _coordinate: &str) -> Option<String> {
+        None
+    }
+
+    /// Where `file_name` of an artifact comes from, to record in jpkg.lock
+    fn artifact_url(
+        &self,
+        _group_id: &str,
+        _artifact_id: &str,
+        _version: &str,
+        _file_name: &str,
+    ) -> Option<String> {
+        None
+    }
+
+    /// Checks the signatures of artifacts, if the backend verifies them
+    fn signatures(&self) -> Option<&Verifier> {
+        None
+    }
+
+    /// The `.asc` signature of `file_name` of an artifact
+    fn signature(
+        &self,
+        group_id: &str,
+        artifact_id: &str,
+        version: &str,
+        file_name: &str,
+    ) -> Result<Vec<u8>> {
+        let path = maven::repository_path(group_id, artifact_id, version, file_name);
+        Err(SignatureError(format!("No signature for {}", path)).into())
+    }
+}
+
+/// Files laid out like a Maven repository, served without a network
+pub trait RepositoryFiles {
+    /// Name to show in messages
+    fn name(&self) -> String;
+
+    /// The file at a repository-relative `path`, if there is one
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Repository-relative paths of every POM
+    fn poms(&self) -> Result<Vec<String>>;
+
+    /// Names of the directories directly inside the repository-relative `dir`
+    fn subdirectories(&self, dir: &str) -> Result<Vec<String>>;
+
+    /// Versions of an artifact, from its version directories, for repositories
+    /// without a `maven-metadata.xml` for it
+    fn versions(&self, group_id: &str, artifact_id: &str) -> Result<Vec<String>> {
+        let dir = format!("{}/{}", group_id.replace('.', "/"), artifact_id);
+        let mut versions = self.subdirectories(&dir)?;
+        versions.sort_by(|a, b| version::compare(a, b));
+        Ok(versions)
+    }
+
+    /// The strongest checksum file next to the file at `path`
+    fn published_checksum(&self, path: &str) -> Result<Option<Checksum>> {
+        for algorithm in Algorithm::ALL {
+            let sidecar = format!("{}.{}", path, algorithm.extension());
+            if let Some(content) = self.read(&sidecar)? {
+                let checksum = Checksum::parse(algorithm, &String::from_utf8_lossy(&content))
+                    .with_context(|| format!("Failed to read {}", sidecar))?;
+                return Ok(Some(checksum));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Copy the file `request` asks for to its `output_path`, if it matches the
+    /// strongest checksum file next to it and its locked checksum
+    fn copy(&self, request: &DownloadRequest) -> Result<Download> {
+        let path = maven::repository_path(
+            &request.group_id,
+            &request.artifact_id,
+            &request.version,
+            &request.file_name,
+        );
+        let bytes = self
+            .read(&path)?
+            .ok_or_else(|| anyhow::anyhow!("Could not find {} in {}", path, self.name()))?;
+
+        let checksum = self.published_checksum(&path)?;
+        if let Some(checksum) = &checksum {
+            checksum.verify(&bytes, &path)?;
+        }
+        request.verify_locked(&bytes, &path)?;
+
+        let part = cache::part_path(&request.output_path);
+        fs::write(&part, &bytes)?;
+        fs::rename(&part, &request.output_path)?;
+        Ok(Download {
+            checksum,
+            signature: None,
+        })
+    }
+}
+
+impl<T: RepositoryFiles> RepositoryBackend for T {
+    fn get_metadata(&self, group_id: &str, artifact_id: &str) -> Result<MavenMetadata> {
+        let path = format!(
+            "{}/{}/maven-metadata.xml",
+            group_id.replace('.', "/"),
+            artifact_id
+        );
+        if let Some(bytes) = self.read(&path)? {
+            return quick_xml::de::from_str(&String::from_utf8_lossy(&bytes))
+                .with_context(|| format!("Failed to parse {}", path));
+        }
+
+        let versions = self.versions(group_id, artifact_id)?;
+        if versions.is_empty() {
+            anyhow::bail!(
+                "No versions of {}:{} in {}",
+                group_id,
+                artifact_id,
+                self.name()
+            );
+        }
+        Ok(MavenMetadata {
+            group_id: group_id.to_string(),
+            artifact_id: artifact_id.to_string(),
+            versioning: Versioning {
+                latest: versions.last().cloned(),
+                release: versions.last().cloned(),
+                versions: Versions { version: versions },
+            },
+        })
+    }
+
+    fn get_pom(&self, group_id: &str, artifact_id: &str, version: &str) -> Result<Project> {
+        let path = maven::artifact_path(group_id, artifact_id, version, "pom");
+        let bytes = self.read(&path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "POM of {}:{}:{} not found in {}",
+                group_id,
+                artifact_id,
+                version,
+                self.name()
+            )
+        })?;
+        quick_xml::de::from_str(&String::from_utf8_lossy(&bytes))
+            .with_context(|| format!("Failed to parse {}", path))
+    }
+
+    fn download_all(
+        &self,
+        requests: &[DownloadRequest],
+        _progress: &MultiProgress,
+    ) -> Vec<Result<Download>> {
+        requests.iter().map(|request| self.copy(request)).collect()
+    }
+
+    /// Artifacts whose group or artifact id contains `query`, with their
+    /// highest version
+    fn search_artifact(&self, query: &str) -> Result<Vec<SearchResult>> {
+        let mut latest: BTreeMap<(String, String), String> = BTreeMap::new();
+        for (group, artifact, version) in self.poms()?.iter().filter_map(|p| pom_coordinate(p)) {
+            if !group.contains(query) && !artifact.contains(query) {
+                continue;
+            }
+            let entry = latest.entry((group, artifact)).or_default();
+            if version::compare(&version, entry).is_gt() {
+                *entry = version;
+            }
+        }
+        Ok(latest
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .map(|((g, a), latest_version)| SearchResult {
+                id: format!("{}:{}", g, a),
+                g,
+                a,
+                latest_version,
+            })
+            .collect())
+    }
+}
+
+/// `(group, artifact, version)` of the POM at a repository-relative `path`,
+/// e.g. `com/google/guava/guava/33.0/guava-33.0.pom`
+fn pom_coordinate(path: &str) -> Option<(String, String, String)> {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    let file = parts.pop()?;
+    let version = parts.pop()?;
+    let artifact = parts.pop()?;
+    if parts.is_empty() || file != format!("{}-{}.pom", artifact, version) {
+        return None;
+    }
+    Some((parts.join("."), artifact.to_string(), version.to_string()))
+}
+
+/// A directory laid out like a Maven repository, e.g. a mirror on a shared drive.
+/// `MavenClient` reads directory and `file://` repositories through it.
+pub struct DirectoryBackend {
+    root: PathBuf,
+}
+
+impl DirectoryBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl RepositoryFiles for DirectoryBackend {
+    fn name(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.root.join(path);
+        match fs::read(&path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    fn poms(&self) -> Result<Vec<String>> {
+        let mut poms = Vec::new();
+        collect_poms(&self.root, &self.root, &mut poms)?;
+        Ok(poms)
+    }
+
+    fn subdirectories(&self, dir: &str) -> Result<Vec<String>> {
+        let dir = self.root.join(dir);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to list {}", dir.display())),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(names)
+    }
+}
+
+fn collect_poms(root: &Path, dir: &Path, poms: &mut Vec<String>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_poms(root, &path, poms)?;
+        } else if path.extension().is_some_and(|ext| ext == "pom")
+            && let Ok(relative) = path.strip_prefix(root)
+        {
+            let components: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            poms.push(components.join("/"));
+        }
+    }
+    Ok(())
+}
+
+/// A repository held in memory, for tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryBackend {
+    /// File contents by repository-relative path
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, path: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.to_string(), content.into());
+        self
+    }
+
+    /// Publish `group:artifact:version` with a POM declaring `dependencies`
+    /// (`group:artifact:version`, the version may be a range) and a jar with
+    /// its `.sha1`
+    pub fn with_artifact(self, coordinate: &str, dependencies: &[&str]) -> Self {
+        let parts: Vec<&str> = coordinate.split(':').collect();
+        let (group, artifact, version) = (parts[0], parts[1], parts[2]);
+        let dependencies: String = dependencies
+            .iter()
+            .map(|dependency| {
+                let parts: Vec<&str> = dependency.splitn(3, ':').collect();
+                format!(
+                    "<dependency><groupId>{}</groupId><artifactId>{}</artifactId><version>{}</version></dependency>",
+                    parts[0], parts[1], parts[2]
+                )
+            })
+            .collect();
+        let pom = format!(
+            "<project><groupId>{}</groupId><artifactId>{}</artifactId><version>{}</version><dependencies>{}</dependencies></project>",
+            group, artifact, version, dependencies
+        );
+        let jar = maven::repository_path(
+            group,
+            artifact,
+            version,
+            &maven::file_name(artifact, version, None, "jar"),
+        );
+        let sha1 = Algorithm::Sha1.digest(coordinate.as_bytes());
+        self.with_file(&maven::artifact_path(group, artifact, version, "pom"), pom)
+            .with_file(&jar, coordinate)
+            .with_file(&format!("{}.sha1", jar), sha1)
+    }
+}
+
+#[cfg(test)]
+impl RepositoryFiles for MemoryBackend {
+    fn name(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn read(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        Ok(self.files.get(path).cloned())
+    }
+
+    fn poms(&self) -> Result<Vec<String>> {
+        Ok(self
+            .files
+            .keys()
+            .filter(|path| path.ends_with(".pom"))
+            .cloned()
+            .collect())
+    }
+
+    fn subdirectories(&self, dir: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", dir);
+        let mut names: Vec<String> = self
+            .files
+            .keys()
+            .filter_map(|path| path.strip_prefix(&prefix)?.split_once('/'))
+            .map(|(name, _)| name.to_string())
+            .collect();
+        names.dedup();
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixtures() -> DirectoryBackend {
+        DirectoryBackend::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/repo"))
+    }
+
+    #[test]
+    fn test_pom_coordinate() {
+        assert_eq!(
+            pom_coordinate("com/google/guava/guava/33.0/guava-33.0.pom"),
+            Some((
+                "com.google.guava".to_string(),
+                "guava".to_string(),
+                "33.0".to_string()
+            ))
+        );
+        assert_eq!(pom_coordinate("guava/33.0/guava-33.0.pom"), None);
+        assert_eq!(
+            pom_coordinate("com/google/guava/guava/33.0/other.pom"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_directory_backend() {
+        let backend = fixtures();
+        let pom = backend.get_pom("com.example", "core", "1.0").unwrap();
+        assert_eq!(pom.artifact_id, "core");
+        assert_eq!(pom.dependencies.dependency.len(), 2);
+        let err = backend.get_pom("com.example", "core", "9.9").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("POM of com.example:core:9.9 not found")
+        );
+
+        // util publishes maven-metadata.xml; log's versions are its directories
+        let util = backend.get_metadata("com.example", "util").unwrap();
+        assert_eq!(util.versioning.versions.version, vec!["1.0", "1.1"]);
+        let log = backend.get_metadata("com.example", "log").unwrap();
+        assert_eq!(log.versioning.versions.version, vec!["2.0"]);
+
+        let results = backend.search_artifact("util").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].g, "com.example");
+        assert_eq!(results[0].latest_version, "1.1");
+        assert_eq!(backend.search_artifact("example").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_download_checks_checksum() {
+        let temp = tempfile::tempdir().unwrap();
+        let request = |artifact: &str| DownloadRequest {
+            group_id: "g".to_string(),
+            artifact_id: artifact.to_string(),
+            version: "1.0".to_string(),
+            file_name: format!("{}-1.0.jar", artifact),
+            output_path: temp.path().join(format!("{}-1.0.jar", artifact)),
+            locked_checksum: None,
+        };
+        let backend = MemoryBackend::new()
+            .with_artifact("g:good:1.0", &[])
+            .with_artifact("g:bad:1.0", &[])
+            .with_file("g/bad/1.0/bad-1.0.jar", "tampered");
+
+        let results = backend.download_all(
+            &[request("good"), request("bad"), request("missing")],
+            &MultiProgress::new(),
+        );
+        let download = results[0].as_ref().unwrap();
+        assert_eq!(
+            download.checksum.as_ref().unwrap().algorithm,
+            Algorithm::Sha1
+        );
+        assert_eq!(
+            fs::read_to_string(temp.path().join("good-1.0.jar")).unwrap(),
+            "g:good:1.0"
+        );
+        let err = results[1].as_ref().err().unwrap().to_string();
+        assert!(err.contains("Checksum mismatch for g/bad/1.0/bad-1.0.jar"));
+        assert!(!temp.path().join("bad-1.0.jar").exists());
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_search_results_are_capped() {
+        let backend = (0..30).fold(MemoryBackend::new(), |backend, i| {
+            backend.with_artifact(&format!("org.springframework:spring-{}:1.0", i), &[])
+        });
+        let results = backend.search_artifact("spring").unwrap();
+        assert_eq!(results.len(), MAX_SEARCH_RESULTS);
+        assert!(backend.search_artifact("guava").unwrap().is_empty());
+    }
+}
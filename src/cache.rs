@@ -1,13 +1,112 @@
-use anyhow::Result;
+use crate::checksum::{Algorithm, Checksum};
+use crate::manifest::MavenLocal;
+use crate::signature::Signature;
+use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Maven's local repository, read as a second cache. It has the same layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalRepository {
+    pub path: PathBuf,
+    /// Never write to it; otherwise downloaded jars and POMs are added to it
+    pub read_only: bool,
+}
+
+static LOCAL_REPOSITORY: Mutex<Option<LocalRepository>> = Mutex::new(None);
+
+#[cfg(test)]
+thread_local! {
+    /// Cache of the current test, so tests never touch ~/.jpkg
+    static TEST_CACHE_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Use `dir` as the cache for the rest of the current test
+#[cfg(test)]
+pub fn use_test_cache_dir(dir: &Path) {
+    TEST_CACHE_DIR.with_borrow_mut(|cache| *cache = Some(dir.to_path_buf()));
+}
 
 /// Get the cache directory path (~/.jpkg/cache/)
 pub fn cache_dir() -> Result<PathBuf> {
+    #[cfg(test)]
+    if let Some(dir) = TEST_CACHE_DIR.with_borrow(Clone::clone) {
+        return Ok(dir);
+    }
     let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
     Ok(home.join(".jpkg").join("cache"))
 }
 
+/// Also look up artifacts in Maven's local repository, as `config` says
+pub fn use_local_repository(config: &MavenLocal) -> Result<()> {
+    let repository = if config.enabled {
+        let path = match &config.path {
+            Some(path) => expand_home(path)?,
+            None => maven_local_repository()?,
+        };
+        Some(LocalRepository {
+            path,
+            read_only: config.read_only,
+        })
+    } else {
+        None
+    };
+    *LOCAL_REPOSITORY.lock().unwrap() = repository;
+    Ok(())
+}
+
+fn local_repository() -> Option<LocalRepository> {
+    LOCAL_REPOSITORY.lock().unwrap().clone()
+}
+
+/// Where Maven keeps downloaded artifacts: `localRepository` from
+/// ~/.m2/settings.xml, or ~/.m2/repository
+fn maven_local_repository() -> Result<PathBuf> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    let settings = home.join(".m2").join("settings.xml");
+    if settings.exists() {
+        let content = fs::read_to_string(&settings)?;
+        if let Some(path) = parse_local_repository(&content, &home)
+            .with_context(|| format!("Failed to read {}", settings.display()))?
+        {
+            return Ok(path);
+        }
+    }
+    Ok(home.join(".m2").join("repository"))
+}
+
+/// `<localRepository>` of a Maven settings.xml, with `${user.home}` filled in
+fn parse_local_repository(settings: &str, home: &Path) -> Result<Option<PathBuf>> {
+    let doc = roxmltree::Document::parse(settings)?;
+    let path = doc
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("localRepository"))
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|path| !path.is_empty());
+    Ok(path.map(|path| PathBuf::from(path.replace("${user.home}", &home.to_string_lossy()))))
+}
+
+fn expand_home(path: &str) -> Result<PathBuf> {
+    match path.strip_prefix("~/") {
+        Some(rest) => {
+            let home =
+                dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+            Ok(home.join(rest))
+        }
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+/// Directory of an artifact version in the cache or a Maven local repository
+fn artifact_dir(root: &Path, group: &str, artifact: &str, version: &str) -> PathBuf {
+    root.join(group.replace('.', "/"))
+        .join(artifact)
+        .join(version)
+}
+
 /// Initialize cache directory
 pub fn init_cache() -> Result<()> {
     let cache = cache_dir()?;
@@ -15,42 +114,210 @@ pub fn init_cache() -> Result<()> {
     Ok(())
 }
 
-/// Get cached JAR path for a specific artifact
-pub fn get_cached_jar(group: &str, artifact: &str, version: &str) -> Result<Option<PathBuf>> {
+/// Get cached path of an artifact file (see `maven::file_name`), in jpkg's
+/// cache or else Maven's local repository
+pub fn get_cached_jar(
+    group: &str,
+    artifact: &str,
+    version: &str,
+    filename: &str,
+) -> Result<Option<PathBuf>> {
     let cache = cache_dir()?;
-    let filename = format!("{}-{}.jar", artifact, version);
-    let cached_path = cache
-        .join(group.replace('.', "/"))
-        .join(artifact)
-        .join(version)
-        .join(&filename);
+    let cached_path = artifact_dir(&cache, group, artifact, version).join(filename);
 
     if cached_path.exists() {
-        Ok(Some(cached_path))
-    } else {
-        Ok(None)
+        return Ok(Some(cached_path));
     }
+    Ok(local_repository()
+        .map(|local| artifact_dir(&local.path, group, artifact, version).join(filename))
+        .filter(|path| path.exists()))
 }
 
 /// Cache a JAR file
-pub fn cache_jar(group: &str, artifact: &str, version: &str, jar_path: &Path) -> Result<()> {
-    let cache = cache_dir()?;
-    let filename = format!("{}-{}.jar", artifact, version);
-    let cache_path = cache
+pub fn cache_jar(
+    group: &str,
+    artifact: &str,
+    version: &str,
+    filename: &str,
+    jar_path: &Path,
+) -> Result<()> {
+    let cache_path = artifact_dir(&cache_dir()?, group, artifact, version);
+
+    fs::create_dir_all(&cache_path)?;
+    let dest = cache_path.join(filename);
+
+    if !dest.exists() {
+        copy_file(jar_path, &dest)?;
+    }
+
+    if let Some(local) = local_repository().filter(|local| !local.read_only) {
+        let local_path = artifact_dir(&local.path, group, artifact, version);
+        fs::create_dir_all(&local_path)?;
+        let dest = local_path.join(filename);
+        if !dest.exists() {
+            copy_file(jar_path, &dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Cache the POM of an artifact as `<artifact>-<version>.pom`, next to its jar
+pub fn cache_pom(group: &str, artifact: &str, version: &str, content: &str) -> Result<()> {
+    let cache_path = artifact_dir(&cache_dir()?, group, artifact, version);
+
+    let filename = format!("{}-{}.pom", artifact, version);
+    fs::create_dir_all(&cache_path)?;
+    write_file(&cache_path.join(&filename), content.as_bytes())?;
+
+    if let Some(local) = local_repository().filter(|local| !local.read_only) {
+        let local_path = artifact_dir(&local.path, group, artifact, version);
+        fs::create_dir_all(&local_path)?;
+        if !local_path.join(&filename).exists() {
+            write_file(&local_path.join(&filename), content.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// The cached POM of an artifact, if any (in either place `get_cached_jar` looks)
+pub fn get_cached_pom(group: &str, artifact: &str, version: &str) -> Result<Option<String>> {
+    let filename = format!("{}-{}.pom", artifact, version);
+    match get_cached_jar(group, artifact, version, &filename)? {
+        Some(path) => Ok(Some(fs::read_to_string(path)?)),
+        None => Ok(None),
+    }
+}
+
+/// Cache the `maven-metadata.xml` listing the versions of an artifact. It is
+/// replaced whenever it is fetched again, since new versions get published.
+pub fn cache_metadata(group: &str, artifact: &str, content: &str) -> Result<()> {
+    let cache_path = cache_dir()?.join(group.replace('.', "/")).join(artifact);
+
+    fs::create_dir_all(&cache_path)?;
+    write_file(&cache_path.join("maven-metadata.xml"), content.as_bytes())
+}
+
+/// The cached `maven-metadata.xml` of an artifact, if any
+pub fn get_cached_metadata(group: &str, artifact: &str) -> Result<Option<String>> {
+    let path = cache_dir()?
         .join(group.replace('.', "/"))
         .join(artifact)
-        .join(version);
+        .join("maven-metadata.xml");
+
+    if path.exists() {
+        Ok(Some(fs::read_to_string(path)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Where a file is written until it is complete: `guava-33.0.jar.part`
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+/// Copy `from` to `to` through a `.part` file, so `to` is never left half-written
+pub fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    let part = part_path(to);
+    fs::copy(from, &part)?;
+    fs::rename(&part, to)?;
+    Ok(())
+}
+
+/// Write `content` to `to` through a `.part` file, like `copy_file`
+fn write_file(to: &Path, content: &[u8]) -> Result<()> {
+    let part = part_path(to);
+    fs::write(&part, content)?;
+    fs::rename(&part, to)?;
+    Ok(())
+}
+
+/// Keep the checksum a cached file was verified against next to it, as
+/// `<filename>.sha1` etc., like a Maven local repository does
+pub fn cache_checksum(
+    group: &str,
+    artifact: &str,
+    version: &str,
+    filename: &str,
+    checksum: &Checksum,
+) -> Result<()> {
+    let cache_path = artifact_dir(&cache_dir()?, group, artifact, version);
 
     fs::create_dir_all(&cache_path)?;
-    let dest = cache_path.join(&filename);
+    let dest = cache_path.join(format!("{}.{}", filename, checksum.algorithm.extension()));
+    fs::write(dest, &checksum.digest)?;
+    Ok(())
+}
 
-    if !dest.exists() {
-        fs::copy(jar_path, dest)?;
+/// The strongest checksum cached for a file, if it was verified when cached.
+/// For files from Maven's local repository, the checksum files Maven kept.
+pub fn get_cached_checksum(
+    group: &str,
+    artifact: &str,
+    version: &str,
+    filename: &str,
+) -> Result<Option<Checksum>> {
+    let cache_path = artifact_dir(&cache_dir()?, group, artifact, version);
+    let dir = if cache_path.join(filename).exists() {
+        cache_path
+    } else {
+        match local_repository() {
+            Some(local) => artifact_dir(&local.path, group, artifact, version),
+            None => cache_path,
+        }
+    };
+
+    for algorithm in Algorithm::ALL {
+        let path = dir.join(format!("{}.{}", filename, algorithm.extension()));
+        if path.exists() {
+            let checksum = Checksum::parse(algorithm, &fs::read_to_string(&path)?)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            return Ok(Some(checksum));
+        }
     }
+    Ok(None)
+}
+
+/// Keep a verified signature next to the cached file: `<filename>.asc`, and the
+/// fingerprint of its signer in `<filename>.signer`, so it isn't checked again
+pub fn cache_signature(
+    group: &str,
+    artifact: &str,
+    version: &str,
+    filename: &str,
+    signature: &Signature,
+) -> Result<()> {
+    let cache_path = artifact_dir(&cache_dir()?, group, artifact, version);
 
+    fs::create_dir_all(&cache_path)?;
+    fs::write(cache_path.join(format!("{}.asc", filename)), &signature.asc)?;
+    fs::write(
+        cache_path.join(format!("{}.signer", filename)),
+        &signature.signer,
+    )?;
     Ok(())
 }
 
+/// Fingerprint of the key that signed a cached file, if its signature was verified
+pub fn get_cached_signer(
+    group: &str,
+    artifact: &str,
+    version: &str,
+    filename: &str,
+) -> Result<Option<String>> {
+    let signer =
+        artifact_dir(&cache_dir()?, group, artifact, version).join(format!("{}.signer", filename));
+
+    if signer.exists() {
+        Ok(Some(fs::read_to_string(signer)?.trim().to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
 /// List all cached artifacts
 pub fn list_cached() -> Result<Vec<String>> {
     let cache = cache_dir()?;
@@ -136,3 +403,26 @@ fn visit_cache_size(dir: &Path, total: &mut u64) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_local_repository() {
+        let home = Path::new("/home/dev");
+        let settings = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <settings xmlns="http://maven.apache.org/SETTINGS/1.0.0">
+              <localRepository>${user.home}/maven-repo</localRepository>
+              <mirrors><mirror><id>m</id></mirror></mirrors>
+            </settings>"#;
+        assert_eq!(
+            parse_local_repository(settings, home).unwrap(),
+            Some(PathBuf::from("/home/dev/maven-repo"))
+        );
+
+        let settings = "<settings><offline>true</offline></settings>";
+        assert_eq!(parse_local_repository(settings, home).unwrap(), None);
+        assert!(parse_local_repository("<settings>", home).is_err());
+    }
+}
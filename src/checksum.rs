@@ -0,0 +1,148 @@
+use anyhow::Result;
+use std::fmt;
+
+/// Algorithms of the checksum files repositories publish next to artifacts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha512,
+    Sha256,
+    Sha1,
+}
+
+impl Algorithm {
+    /// Strongest first
+    pub const ALL: [Algorithm; 3] = [Algorithm::Sha512, Algorithm::Sha256, Algorithm::Sha1];
+
+    /// Extension of the checksum file, e.g. `guava-33.0.jar.sha1`
+    pub fn extension(self) -> &'static str {
+        match self {
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha1 => "sha1",
+        }
+    }
+
+    /// Hex digest of `bytes`
+    pub fn digest(self, bytes: &[u8]) -> String {
+        match self {
+            Algorithm::Sha512 => {
+                use sha2::Digest;
+                hex::encode(sha2::Sha512::digest(bytes))
+            }
+            Algorithm::Sha256 => {
+                use sha2::Digest;
+                hex::encode(sha2::Sha256::digest(bytes))
+            }
+            Algorithm::Sha1 => {
+                use sha1::Digest;
+                hex::encode(sha1::Sha1::digest(bytes))
+            }
+        }
+    }
+
+    fn hex_len(self) -> usize {
+        match self {
+            Algorithm::Sha512 => 128,
+            Algorithm::Sha256 => 64,
+            Algorithm::Sha1 => 40,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// A digest as published in a checksum file; displayed as `sha1:<hex>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub digest: String,
+}
+
+impl Checksum {
+    /// Parse the content of a checksum file. Besides the bare digest, some tools
+    /// write `<digest>  <file name>`.
+    pub fn parse(algorithm: Algorithm, content: &str) -> Result<Self> {
+        let digest = content
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if digest.len() != algorithm.hex_len() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Invalid {} checksum: '{}'", algorithm, content.trim());
+        }
+        Ok(Self { algorithm, digest })
+    }
+
+    /// Fail unless `bytes` have this digest; `source` names them in the error
+    pub fn verify(&self, bytes: &[u8], source: &str) -> Result<()> {
+        let actual = self.algorithm.digest(bytes);
+        if actual != self.digest {
+            anyhow::bail!(
+                "Checksum mismatch for {}: expected {} {}, got {}",
+                source,
+                self.algorithm,
+                self.digest,
+                actual
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_checksum_files() {
+        let sha1 = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        assert_eq!(
+            Checksum::parse(Algorithm::Sha1, &format!("{}\n", sha1.to_uppercase())).unwrap(),
+            Checksum {
+                algorithm: Algorithm::Sha1,
+                digest: sha1.to_string()
+            }
+        );
+        assert_eq!(
+            Checksum::parse(Algorithm::Sha1, &format!("{}  abc.jar", sha1))
+                .unwrap()
+                .digest,
+            sha1
+        );
+
+        // An HTML error page served with status 200 is not a checksum
+        assert!(Checksum::parse(Algorithm::Sha1, "<html>Not Found</html>").is_err());
+        assert!(Checksum::parse(Algorithm::Sha256, sha1).is_err());
+        assert!(Checksum::parse(Algorithm::Sha1, "").is_err());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        for algorithm in Algorithm::ALL {
+            let checksum = Checksum::parse(algorithm, &algorithm.digest(b"abc")).unwrap();
+            assert!(checksum.verify(b"abc", "abc.jar").is_ok());
+            let err = checksum.verify(b"abd", "abc.jar").unwrap_err();
+            assert!(err.to_string().contains("Checksum mismatch for abc.jar"));
+        }
+        assert_eq!(
+            Algorithm::Sha1.digest(b"abc"),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            Checksum::parse(Algorithm::Sha1, "a9993e364706816aba3e25717850c26c9cd0d89d")
+                .unwrap()
+                .to_string(),
+            "sha1:a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! The resolved dependency graph and the ways `jpkg tree` prints it
+
+use crate::manifest::Scope;
+use anyhow::Context;
+use colored::Colorize;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A selected artifact (or the project) depends on another selected artifact
+#[derive(Debug, Clone)]
+pub struct GraphEdge {
+    /// `group:artifact:version`, or `DependencyGraph::package` for jpkg.json
+    pub parent: String,
+    /// `group:artifact:version` that was selected
+    pub child: String,
+    /// The version or range the parent asked for
+    pub requested: String,
+    /// The parent asked for a version that lost to `child`'s
+    pub conflict: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DependencyGraph {
+    /// `name vversion` of the project
+    pub package: String,
+    pub scopes: HashMap<String, Scope>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// One line of the tree; the same artifact can appear under several parents
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Scope>,
+    /// Why the children aren't shown: "duplicate" (already shown above) or "conflict"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub omitted: Option<&'static str>,
+    /// For a conflict, the version used instead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<TreeNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum TreeFormat {
+    Text,
+    Json,
+    Dot,
+}
+
+impl DependencyGraph {
+    /// `key`'s dependencies, or its dependents when `inverted`
+    fn neighbours(&self, key: &str, inverted: bool) -> Vec<&GraphEdge> {
+        self.edges
+            .iter()
+            .filter(|e| {
+                if inverted {
+                    e.child == key
+                } else {
+                    e.parent == key
+                }
+            })
+            .collect()
+    }
+
+    /// Selected `group:artifact:version` of each dependency of `key` (or of the project)
+    pub fn children(&self, key: &str) -> Vec<String> {
+        let mut children: Vec<String> = self
+            .neighbours(key, false)
+            .into_iter()
+            .map(|edge| edge.child.clone())
+            .collect();
+        children.sort();
+        children.dedup();
+        children
+    }
+
+    /// Selected `group:artifact:version` for `artifact` (`group:artifact` or a full key)
+    pub fn find(&self, artifact: &str) -> Option<&String> {
+        self.scopes.keys().find(|key| {
+            *key == artifact || key.rsplit_once(':').is_some_and(|(a, _)| a == artifact)
+        })
+    }
+
+    /// The tree below the project, or with `invert` the tree of dependents above
+    /// `invert` (every artifact nothing depends on if it's empty).
+    /// `depth` limits the number of levels shown.
+    pub fn tree(&self, depth: Option<usize>, invert: Option<&str>) -> anyhow::Result<TreeNode> {
+        let mut expanded = HashSet::new();
+        let tops: Vec<String> = match invert {
+            None => return Ok(self.node(&self.package, None, 0, depth, false, &mut expanded)),
+            Some("") => {
+                let mut leaves: Vec<String> = self
+                    .scopes
+                    .keys()
+                    .filter(|key| self.neighbours(key, false).is_empty())
+                    .cloned()
+                    .collect();
+                leaves.sort();
+                leaves
+            }
+            Some(artifact) => vec![self.selected(artifact)?.clone()],
+        };
+
+        let dependencies = tops
+            .iter()
+            .map(|key| self.node(key, None, 1, depth, true, &mut expanded))
+            .collect();
+        Ok(TreeNode {
+            id: self.package.clone(),
+            scope: None,
+            omitted: None,
+            selected: None,
+            dependencies,
+        })
+    }
+
+    /// Selected `group:artifact:version` of `artifact`, or an error if it wasn't resolved
+    pub fn selected(&self, artifact: &str) -> anyhow::Result<&String> {
+        self.find(artifact)
+            .with_context(|| format!("{} is not a dependency", artifact))
+    }
+
+    fn node(
+        &self,
+        key: &str,
+        conflict: Option<&str>,
+        level: usize,
+        depth: Option<usize>,
+        inverted: bool,
+        expanded: &mut HashSet<String>,
+    ) -> TreeNode {
+        let mut node = TreeNode {
+            id: key.to_string(),
+            scope: self.scopes.get(key).copied(),
+            omitted: None,
+            selected: None,
+            dependencies: Vec::new(),
+        };
+
+        // Shown as what was asked for, with the winner alongside
+        if let Some(requested) = conflict {
+            let (artifact, selected) = key.rsplit_once(':').unwrap_or((key, ""));
+            node.id = format!("{}:{}", artifact, requested);
+            node.omitted = Some("conflict");
+            node.selected = Some(selected.to_string());
+            return node;
+        }
+
+        let mut neighbours = self.neighbours(key, inverted);
+        if neighbours.is_empty() || depth.is_some_and(|depth| level >= depth) {
+            return node;
+        }
+        if !expanded.insert(key.to_string()) {
+            node.omitted = Some("duplicate");
+            return node;
+        }
+
+        neighbours.sort_by_key(|e| inverted.then(|| e.parent.clone()));
+        for edge in neighbours {
+            let child = if inverted {
+                self.node(&edge.parent, None, level + 1, depth, inverted, expanded)
+            } else {
+                let conflict = edge.conflict.then_some(edge.requested.as_str());
+                self.node(&edge.child, conflict, level + 1, depth, inverted, expanded)
+            };
+            node.dependencies.push(child);
+        }
+        node
+    }
+
+    /// Chains of edges from the project down to `key`, shortest first.
+    /// Stops looking after `limit` chains.
+    pub fn paths_to(&self, key: &str, limit: usize) -> Vec<Vec<&GraphEdge>> {
+        let mut paths = Vec::new();
+        self.walk_back(key, &mut Vec::new(), &mut paths, limit);
+        for path in &mut paths {
+            path.reverse();
+        }
+        paths.sort_by(|a, b| {
+            a.len().cmp(&b.len()).then_with(|| {
+                let ids = |p: &[&GraphEdge]| p.iter().map(|e| e.child.clone()).collect::<Vec<_>>();
+                ids(a).cmp(&ids(b))
+            })
+        });
+        paths
+    }
+
+    fn walk_back<'g>(
+        &'g self,
+        key: &str,
+        current: &mut Vec<&'g GraphEdge>,
+        paths: &mut Vec<Vec<&'g GraphEdge>>,
+        limit: usize,
+    ) {
+        if paths.len() >= limit {
+            return;
+        }
+        if key == self.package {
+            paths.push(current.clone());
+            return;
+        }
+        for edge in self.neighbours(key, true) {
+            // Dependency cycles are legal in Maven; don't go round them
+            if edge.parent == edge.child || current.iter().any(|e| e.child == edge.parent) {
+                continue;
+            }
+            current.push(edge);
+            self.walk_back(&edge.parent, current, paths, limit);
+            current.pop();
+        }
+    }
+}
+
+/// Indented tree, like `cargo tree`
+pub fn render_text(root: &TreeNode) -> String {
+    let mut out = format!("{}\n", root.id.bold());
+    let mut duplicates = false;
+    render_children(&root.dependencies, "", &mut out, &mut duplicates);
+    if duplicates {
+        out.push_str(&format!(
+            "\n{}\n",
+            "(*) dependencies omitted (listed previously)".dimmed()
+        ));
+    }
+    out
+}
+
+fn render_children(nodes: &[TreeNode], prefix: &str, out: &mut String, duplicates: &mut bool) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let branch = if last { "└── " } else { "├── " };
+
+        let mut line = node.id.clone();
+        if let Some(scope) = node.scope {
+            line.push_str(&format!(" {}", format!("[{}]", scope).dimmed()));
+        }
+        match node.omitted {
+            Some("duplicate") => {
+                *duplicates = true;
+                line.push_str(&format!(" {}", "(*)".dimmed()));
+            }
+            Some(_) => {
+                let selected = node.selected.as_deref().unwrap_or_default();
+                line = format!(
+                    "{} {}",
+                    node.id.yellow(),
+                    format!("(omitted for conflict with {})", selected).yellow()
+                );
+            }
+            None => {}
+        }
+        out.push_str(&format!("{}{}{}\n", prefix, branch, line));
+
+        let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+        render_children(&node.dependencies, &child_prefix, out, duplicates);
+    }
+}
+
+/// One chain from `paths_to`, one hop per line, with the version each hop asked for
+pub fn render_path(package: &str, path: &[&GraphEdge]) -> String {
+    let mut out = format!("  {}\n", package.bold());
+    for (i, edge) in path.iter().enumerate() {
+        let (artifact, selected) = edge.child.rsplit_once(':').unwrap_or((&edge.child, ""));
+        let asked = format!("{}:{}", artifact, edge.requested);
+        let hop = if edge.conflict {
+            format!(
+                "{} {}",
+                asked.yellow(),
+                format!("(conflict, {} used)", selected).yellow()
+            )
+        } else if edge.requested != selected {
+            format!("{} {}", asked, format!("→ {}", selected).dimmed())
+        } else {
+            asked
+        };
+        out.push_str(&format!("  {}└─ {}\n", "   ".repeat(i), hop));
+    }
+    out
+}
+
+/// Graphviz digraph of the edges shown in the tree
+pub fn render_dot(root: &TreeNode) -> String {
+    let mut out = String::from("digraph dependencies {\n    rankdir=LR;\n    node [shape=box];\n");
+    let mut seen = HashSet::new();
+    dot_edges(root, &mut out, &mut seen);
+    out.push_str("}\n");
+    out
+}
+
+fn dot_edges(node: &TreeNode, out: &mut String, seen: &mut HashSet<(String, String)>) {
+    for child in &node.dependencies {
+        if seen.insert((node.id.clone(), child.id.clone())) {
+            let style = if child.omitted == Some("conflict") {
+                format!(
+                    " [style=dashed, label=\"conflict: {}\"]",
+                    child.selected.as_deref().unwrap_or_default()
+                )
+            } else {
+                String::new()
+            };
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                node.id, child.id, style
+            ));
+        }
+        dot_edges(child, out, seen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // app -> a:1.0 -> c:2.0, app -> b:1.0 -> c:1.0 (lost to 2.0), b:1.0 -> a:[1,2)
+    fn graph() -> DependencyGraph {
+        let edge = |parent: &str, child: &str, requested: &str, conflict| GraphEdge {
+            parent: parent.to_string(),
+            child: child.to_string(),
+            requested: requested.to_string(),
+            conflict,
+        };
+        DependencyGraph {
+            package: "app v1.0".to_string(),
+            scopes: [
+                ("g:a:1.0", Scope::Compile),
+                ("g:b:1.0", Scope::Test),
+                ("g:c:2.0", Scope::Compile),
+            ]
+            .into_iter()
+            .map(|(k, s)| (k.to_string(), s))
+            .collect(),
+            edges: vec![
+                edge("app v1.0", "g:a:1.0", "1.0", false),
+                edge("app v1.0", "g:b:1.0", "1.0", false),
+                edge("g:a:1.0", "g:c:2.0", "2.0", false),
+                edge("g:b:1.0", "g:c:2.0", "1.0", true),
+                edge("g:b:1.0", "g:a:1.0", "[1,2)", false),
+            ],
+        }
+    }
+
+    fn ids(node: &TreeNode) -> Vec<&str> {
+        node.dependencies.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tree_marks_duplicates_and_conflicts() {
+        let tree = graph().tree(None, None).unwrap();
+        assert_eq!(ids(&tree), vec!["g:a:1.0", "g:b:1.0"]);
+        assert_eq!(graph().children("g:b:1.0"), vec!["g:a:1.0", "g:c:2.0"]);
+
+        let b = &tree.dependencies[1];
+        assert_eq!(ids(b), vec!["g:c:1.0", "g:a:1.0"]);
+        assert_eq!(b.dependencies[0].omitted, Some("conflict"));
+        assert_eq!(b.dependencies[0].selected.as_deref(), Some("2.0"));
+        assert_eq!(b.dependencies[1].omitted, Some("duplicate"));
+
+        let text = render_text(&tree);
+        assert!(text.contains("omitted for conflict with 2.0"));
+        assert!(text.contains("(*)"));
+    }
+
+    #[test]
+    fn test_tree_depth() {
+        let tree = graph().tree(Some(1), None).unwrap();
+        assert!(tree.dependencies.iter().all(|n| n.dependencies.is_empty()));
+        assert!(tree.dependencies.iter().all(|n| n.omitted.is_none()));
+    }
+
+    #[test]
+    fn test_inverted_tree() {
+        let tree = graph().tree(None, Some("g:c")).unwrap();
+        let c = &tree.dependencies[0];
+        assert_eq!(c.id, "g:c:2.0");
+        assert_eq!(ids(c), vec!["g:a:1.0", "g:b:1.0"]);
+        // a is declared in jpkg.json and needed by b
+        assert_eq!(ids(&c.dependencies[0]), vec!["app v1.0", "g:b:1.0"]);
+
+        let leaves = graph().tree(None, Some("")).unwrap();
+        assert_eq!(ids(&leaves), vec!["g:c:2.0"]);
+        assert!(graph().tree(None, Some("g:missing")).is_err());
+    }
+
+    #[test]
+    fn test_paths_to() {
+        let graph = graph();
+        let paths = graph.paths_to("g:c:2.0", 10);
+        let chains: Vec<Vec<(&str, &str)>> = paths
+            .iter()
+            .map(|p| {
+                p.iter()
+                    .map(|e| (e.child.as_str(), e.requested.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            chains,
+            vec![
+                vec![("g:a:1.0", "1.0"), ("g:c:2.0", "2.0")],
+                vec![("g:b:1.0", "1.0"), ("g:c:2.0", "1.0")],
+                vec![("g:b:1.0", "1.0"), ("g:a:1.0", "[1,2)"), ("g:c:2.0", "2.0")],
+            ]
+        );
+        assert_eq!(graph.paths_to("g:c:2.0", 1).len(), 1);
+
+        let text = render_path(&graph.package, &paths[1]);
+        assert!(text.contains("g:c:1.0"));
+        assert!(text.contains("conflict, 2.0 used"));
+        let text = render_path(&graph.package, &paths[2]);
+        assert!(text.contains("g:a:[1,2)"));
+    }
+
+    #[test]
+    fn test_json_and_dot() {
+        let tree = graph().tree(None, None).unwrap();
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["dependencies"][1]["scope"], "test");
+        assert_eq!(
+            json["dependencies"][1]["dependencies"][0]["omitted"],
+            "conflict"
+        );
+
+        let dot = render_dot(&tree);
+        assert!(dot.contains("\"g:a:1.0\" -> \"g:c:2.0\";"));
+        assert!(dot.contains("\"g:b:1.0\" -> \"g:c:1.0\" [style=dashed"));
+    }
+}
@@ -0,0 +1,705 @@
+use crate::backend::RepositoryBackend;
+use crate::cache;
+use crate::checksum::Checksum;
+use crate::credentials;
+use crate::lockfile::{self, LockFile, LockedPackage};
+use crate::manifest::{ConflictStrategy, Manifest, Scope};
+use crate::maven::{self, DownloadRequest, MavenClient};
+use crate::resolver::Resolver;
+use crate::signature::Verifier;
+use anyhow::Result;
+use colored::Colorize;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn install(frozen: bool, offline: bool, strategy: Option<ConflictStrategy>) -> Result<()> {
+    if !Path::new("jpkg.json").exists() {
+        anyhow::bail!("{}", "jpkg.json not found. Run 'jpkg init' first.".red());
+    }
+    let content = fs::read_to_string("jpkg.json")?;
+    let manifest: Manifest = serde_json::from_str(&content)?;
+
+    let previous_lock = LockFile::load()?;
+    let client = repository_client(&manifest, &previous_lock)?.with_offline(offline);
+    install_into(
+        Path::new("."),
+        &client,
+        &manifest,
+        &previous_lock,
+        frozen,
+        offline,
+        strategy,
+    )
+}
+
+/// Install the dependencies of `manifest` into the project in `dir`, with
+/// artifacts from `backend`. `previous_lock` is the project's jpkg.lock, or an
+/// empty one if it has none.
+pub fn install_into(
+    dir: &Path,
+    backend: &dyn RepositoryBackend,
+    manifest: &Manifest,
+    previous_lock: &LockFile,
+    frozen: bool,
+    offline: bool,
+    strategy: Option<ConflictStrategy>,
+) -> Result<()> {
+    let strategy = strategy.unwrap_or(manifest.conflict_strategy);
+    let inputs = lockfile::inputs_hash(manifest, strategy)?;
+    let lock_path = dir.join("jpkg.lock");
+
+    // --frozen installs exactly what the lock says, or nothing
+    if frozen {
+        if !lock_path.exists() {
+            anyhow::bail!(
+                "{}",
+                "jpkg.lock not found; --frozen only installs from an existing lock".red()
+            );
+        }
+        // Older locks didn't record what they were resolved from
+        if previous_lock.inputs_hash.is_none() {
+            anyhow::bail!(
+                "{}",
+                "jpkg.lock was written by an older jpkg and can't be checked against jpkg.json; run 'jpkg install' without --frozen to regenerate it".red()
+            );
+        }
+        if !previous_lock.is_current(&inputs) {
+            anyhow::bail!(
+                "{}",
+                "jpkg.json has changed since jpkg.lock was written; run 'jpkg install' without --frozen to update the lock".red()
+            );
+        }
+    }
+
+    // Nothing that affects resolution changed: the lock already has the answer,
+    // and every file installed must match the checksum it pins
+    let pinned = previous_lock.is_current(&inputs);
+    let mut lockfile = if pinned {
+        println!(
+            "{}",
+            format!(
+                "📦 jpkg.lock is up to date ({} packages)",
+                previous_lock.packages.len()
+            )
+            .cyan()
+        );
+        previous_lock.clone()
+    } else {
+        resolve(backend, manifest, strategy)?
+    };
+    lockfile.inputs_hash = Some(inputs);
+
+    cache::init_cache()?;
+
+    let progress = MultiProgress::new();
+    let pb = progress.add(ProgressBar::new(lockfile.packages.len() as u64));
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+
+    // Install what's cached first; everything else is downloaded together afterwards
+    let mut installed: Vec<(String, PathBuf, String)> = Vec::new();
+    let mut checksums: HashMap<String, Checksum> = HashMap::new();
+    let mut downloads: Vec<DownloadRequest> = Vec::new();
+    for (pkg, locked) in &lockfile.packages {
+        let parts: Vec<&str> = pkg.split(':').collect();
+        let group = parts[0];
+        let artifact = parts[1];
+        let version = parts[2];
+
+        // POM-only artifacts (parents, aggregators) have nothing to put on a classpath
+        let Some(extension) = maven::packaging_extension(&locked.packaging) else {
+            pb.inc(1);
+            continue;
+        };
+
+        // Each scope has its own directory, so each classpath gets the right jars
+        let filename = maven::file_name(artifact, version, locked.classifier.as_deref(), extension);
+        let lib_dir = dir.join(locked.scope.lib_dir());
+        fs::create_dir_all(&lib_dir)?;
+        let path = lib_dir.join(&filename);
+        let pin = pinned.then_some(locked.checksum.as_str());
+        if path.exists() && verify_pinned(pkg, pin, &path).is_err() {
+            progress.suspend(|| {
+                println!(
+                    "  {} {} {}",
+                    "⚠".yellow(),
+                    path.strip_prefix(dir).unwrap_or(&path).display(),
+                    "(differs from jpkg.lock, reinstalling)".dimmed()
+                )
+            });
+            fs::remove_file(&path)?;
+        }
+
+        // Try cache first
+        if let Some(cached_path) = cache::get_cached_jar(group, artifact, version, &filename)? {
+            let checksum = cache::get_cached_checksum(group, artifact, version, &filename)?;
+            if let Some(verifier) = backend.signatures() {
+                verify_cached_signature(backend, verifier, pkg, &filename, &cached_path, offline)
+                    .map_err(|e| anyhow::anyhow!("{}", e.to_string().red()))?;
+            }
+            if !path.exists() {
+                // Checked again, in case the cache was tampered with since
+                if let Some(checksum) = &checksum {
+                    checksum
+                        .verify(&fs::read(&cached_path)?, &cached_path.display().to_string())
+                        .map_err(|e| anyhow::anyhow!("{}", e.to_string().red()))?;
+                }
+                verify_pinned(pkg, pin, &cached_path)
+                    .map_err(|e| anyhow::anyhow!("{}", e.to_string().red()))?;
+                cache::copy_file(&cached_path, &path)?;
+            }
+            if let Some(checksum) = checksum {
+                checksums.insert(pkg.clone(), checksum);
+            }
+            pb.inc(1);
+        } else if offline {
+            // Offline mode: fail if not in cache
+            anyhow::bail!(
+                "{}",
+                format!("Artifact {} not in cache (offline mode)", pkg).red()
+            );
+        } else if !path.exists() {
+            downloads.push(DownloadRequest {
+                group_id: group.to_string(),
+                artifact_id: artifact.to_string(),
+                version: version.to_string(),
+                file_name: filename.clone(),
+                output_path: path.clone(),
+                // A jar that differs from the lock must not get into lib/ or the cache
+                locked_checksum: pin.map(str::to_string),
+            });
+        } else {
+            pb.inc(1);
+        }
+        installed.push((pkg.clone(), path, filename));
+    }
+
+    // Downloads run concurrently, and each is verified before it is written
+    let results = backend.download_all(&downloads, &progress);
+    for (request, result) in downloads.iter().zip(results) {
+        let download = result?;
+        let group = &request.group_id;
+        let artifact = &request.artifact_id;
+        let version = &request.version;
+        let filename = &request.file_name;
+        let pkg = format!("{}:{}:{}", group, artifact, version);
+
+        if let Some(signature) = &download.signature {
+            cache::cache_signature(group, artifact, version, filename, signature)?;
+        }
+        match download.checksum {
+            Some(checksum) => {
+                cache::cache_checksum(group, artifact, version, filename, &checksum)?;
+                checksums.insert(pkg, checksum);
+            }
+            None => progress.suspend(|| {
+                println!(
+                    "  {} {} {}",
+                    "⚠".yellow(),
+                    pkg,
+                    "(no checksum published, not verified)".dimmed()
+                )
+            }),
+        }
+        // Cache the downloaded JAR
+        cache::cache_jar(group, artifact, version, filename, &request.output_path)?;
+        pb.inc(1);
+    }
+
+    let kept: HashSet<PathBuf> = installed.iter().map(|(_, path, _)| path.clone()).collect();
+    for (pkg, path, filename) in installed {
+        // Whatever ends up in lib/ must be the exact file that was locked
+        if pinned {
+            previous_lock
+                .verify_package(&pkg, &path)
+                .map_err(|e| anyhow::anyhow!("{}", e.to_string().red()))?;
+        }
+        if frozen {
+            continue;
+        }
+
+        // Add to lock file
+        let mut locked = lockfile.packages[&pkg].clone();
+        let parts: Vec<&str> = pkg.split(':').collect();
+        if let Some(repository) = backend.origin(&pkg) {
+            locked.repository = Some(repository);
+        }
+        locked.url = backend.artifact_url(parts[0], parts[1], parts[2], &filename);
+        if let Some(checksum) = checksums.remove(&pkg) {
+            locked.repository_checksum = Some(checksum.to_string());
+        }
+        if pinned {
+            // The file was checked against the locked checksum, which stays
+            lockfile.packages.insert(pkg, locked);
+        } else {
+            lockfile.add_package(pkg, Some(&path), locked)?;
+        }
+    }
+    pb.finish_with_message(format!("{}", "✓ Done!".green().bold()));
+    remove_stale_jars(dir, &kept)?;
+
+    // Save lock file
+    if frozen {
+        println!("{}", "✓ Verified against jpkg.lock".green());
+    } else {
+        lockfile.save_to(&lock_path)?;
+        println!("{}", "✓ Saved jpkg.lock".green());
+    }
+    Ok(())
+}
+
+/// Check the file at `path` against the checksum jpkg.lock pins for `pkg`, if any
+fn verify_pinned(pkg: &str, pin: Option<&str>, path: &Path) -> Result<()> {
+    match pin {
+        Some(checksum) => {
+            lockfile::verify_sha256(pkg, checksum, &fs::read(path)?, &path.display().to_string())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Remove the jars in lib/ and its scope directories that weren't just
+/// installed there: older versions, and jars whose scope changed
+fn remove_stale_jars(dir: &Path, kept: &HashSet<PathBuf>) -> Result<()> {
+    for scope in Scope::ALL {
+        let Ok(entries) = fs::read_dir(dir.join(scope.lib_dir())) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_file()
+                && path.extension().is_some_and(|e| e == "jar")
+                && !kept.contains(&path)
+            {
+                fs::remove_file(&path)?;
+                println!(
+                    "  {} {}",
+                    "-".red(),
+                    path.strip_prefix(dir).unwrap_or(&path).display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check the signature of a cached file of `pkg`, unless that was done when it was cached
+fn verify_cached_signature(
+    backend: &dyn RepositoryBackend,
+    verifier: &Verifier,
+    pkg: &str,
+    filename: &str,
+    cached_path: &Path,
+    offline: bool,
+) -> Result<()> {
+    let parts: Vec<&str> = pkg.split(':').collect();
+    let (group, artifact, version) = (parts[0], parts[1], parts[2]);
+
+    if let Some(signer) = cache::get_cached_signer(group, artifact, version, filename)? {
+        // Still checked against the pins, which may have changed since
+        return verifier.check_signer(group, &signer, filename);
+    }
+    if offline {
+        anyhow::bail!(
+            "{} was cached without its signature, which can't be fetched offline",
+            pkg
+        );
+    }
+    let asc = backend.signature(group, artifact, version, filename)?;
+    let signature = verifier.verify(group, &fs::read(cached_path)?, &asc, filename)?;
+    cache::cache_signature(group, artifact, version, filename, &signature)
+}
+
+/// Run the resolver and describe its result as a lock file (without checksums yet)
+fn resolve(
+    backend: &dyn RepositoryBackend,
+    manifest: &Manifest,
+    strategy: ConflictStrategy,
+) -> Result<LockFile> {
+    let mut resolver = Resolver::new(backend, manifest).with_strategy(strategy);
+
+    println!(
+        "{}",
+        format!(
+            "📦 Resolving dependencies (conflict strategy: {})...",
+            resolver.strategy()
+        )
+        .cyan()
+    );
+    let resolved = resolver.resolve()?;
+
+    println!(
+        "{}",
+        format!("✓ Resolved {} packages:", resolved.len()).green()
+    );
+    for pkg in &resolved {
+        match resolver.scope(pkg) {
+            Scope::Compile => println!("  {} {}", "•".blue(), pkg),
+            scope => println!(
+                "  {} {} {}",
+                "•".blue(),
+                pkg,
+                format!("({})", scope).dimmed()
+            ),
+        }
+    }
+    for conflict in resolver.conflicts() {
+        println!(
+            "  {} {} {} {}",
+            "↳".yellow(),
+            conflict.artifact,
+            conflict.selected.green(),
+            format!("(over {})", conflict.rejected.join(", ")).dimmed()
+        );
+    }
+
+    // The graph goes in the lock too, so it can be rebuilt without resolving
+    let graph = resolver.graph();
+    let mut lockfile = LockFile::new();
+    lockfile.dependencies = graph.children(&graph.package);
+    for pkg in resolved {
+        let version = pkg.rsplit(':').next().unwrap_or_default().to_string();
+        let locked = LockedPackage {
+            version,
+            checksum: String::new(),
+            dependencies: graph.children(&pkg),
+            repository: backend.origin(&pkg),
+            url: None,
+            repository_checksum: None,
+            packaging: resolver.packaging(&pkg).to_string(),
+            classifier: resolver.classifier(&pkg).map(str::to_string),
+            scope: resolver.scope(&pkg),
+        };
+        lockfile.packages.insert(pkg, locked);
+    }
+    Ok(lockfile)
+}
+
+/// Client for the manifest's repositories; artifacts are fetched from the
+/// repository that served them last time. Also sets up the cache to read
+/// through to Maven's local repository, if the manifest allows it.
+pub fn repository_client(manifest: &Manifest, lock: &LockFile) -> Result<MavenClient> {
+    cache::use_local_repository(&manifest.maven_local)?;
+    let client = MavenClient::with_repositories(&manifest.repositories)
+        .with_credentials(credentials::load()?)
+        .with_signatures(manifest.signatures.clone())
+        .with_retry(manifest.retry);
+    for (key, locked) in &lock.packages {
+        if let Some(repository) = &locked.repository {
+            client.prefer(key, repository);
+        }
+    }
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+    use crate::checksum::Algorithm;
+
+    fn manifest() -> Manifest {
+        serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0"},
+                "test-dependencies": {"g:t": "1.0"}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_install_and_reinstall_from_lock() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &["g:b:[1.0,2.0)"])
+            .with_artifact("g:b:1.0", &[])
+            .with_artifact("g:b:1.2", &[])
+            .with_artifact("g:t:1.0", &[]);
+
+        install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &LockFile::new(),
+            false,
+            false,
+            None,
+        )
+        .unwrap();
+        for jar in ["lib/a-1.0.jar", "lib/b-1.2.jar", "lib/test/t-1.0.jar"] {
+            assert!(
+                project.path().join(jar).exists(),
+                "{} should be installed",
+                jar
+            );
+        }
+        let lock = LockFile::parse(&fs::read_to_string(project.path().join("jpkg.lock")).unwrap())
+            .unwrap();
+        assert_eq!(lock.dependencies, vec!["g:a:1.0", "g:t:1.0"]);
+        let b = &lock.packages["g:b:1.2"];
+        assert!(!b.checksum.is_empty());
+        assert!(
+            b.repository_checksum
+                .as_deref()
+                .unwrap()
+                .starts_with("sha1:")
+        );
+
+        // Everything is cached and locked now: --frozen needs nothing from the backend
+        fs::remove_dir_all(project.path().join("lib")).unwrap();
+        install_into(
+            project.path(),
+            &MemoryBackend::new(),
+            &manifest(),
+            &lock,
+            true,
+            true,
+            None,
+        )
+        .unwrap();
+        assert!(project.path().join("lib/b-1.2.jar").exists());
+    }
+
+    #[test]
+    fn test_upgrade_removes_the_old_jar() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &[])
+            .with_artifact("g:a:1.1", &[])
+            .with_artifact("g:t:1.0", &[]);
+        install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &LockFile::new(),
+            false,
+            false,
+            None,
+        )
+        .unwrap();
+        assert!(project.path().join("lib/a-1.0.jar").exists());
+
+        // g:a moves to 1.1, and g:t from test to compile scope
+        let upgraded: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.1", "g:t": "1.0"}
+            }"#,
+        )
+        .unwrap();
+        let lock = LockFile::parse(&fs::read_to_string(project.path().join("jpkg.lock")).unwrap())
+            .unwrap();
+        install_into(
+            project.path(),
+            &backend,
+            &upgraded,
+            &lock,
+            false,
+            false,
+            None,
+        )
+        .unwrap();
+        assert!(project.path().join("lib/a-1.1.jar").exists());
+        assert!(project.path().join("lib/t-1.0.jar").exists());
+        assert!(!project.path().join("lib/a-1.0.jar").exists());
+        assert!(!project.path().join("lib/test/t-1.0.jar").exists());
+    }
+
+    #[test]
+    fn test_current_lock_pins_installed_files() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &[])
+            .with_artifact("g:t:1.0", &[]);
+        install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &LockFile::new(),
+            false,
+            false,
+            None,
+        )
+        .unwrap();
+        let lock_path = project.path().join("jpkg.lock");
+        let lock = LockFile::parse(&fs::read_to_string(&lock_path).unwrap()).unwrap();
+        let jar = project.path().join("lib/a-1.0.jar");
+        let original = fs::read(&jar).unwrap();
+
+        // A changed jar in lib/ is replaced, and the lock keeps its checksum
+        fs::write(&jar, "tampered").unwrap();
+        install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &lock,
+            false,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&jar).unwrap(), original);
+        let relocked = LockFile::parse(&fs::read_to_string(&lock_path).unwrap()).unwrap();
+        assert_eq!(
+            relocked.packages["g:a:1.0"].checksum,
+            lock.packages["g:a:1.0"].checksum
+        );
+
+        // A changed cached jar is rejected, even with a matching checksum file
+        let cached = cache::get_cached_jar("g", "a", "1.0", "a-1.0.jar")
+            .unwrap()
+            .unwrap();
+        fs::write(&cached, "tampered").unwrap();
+        fs::write(
+            format!("{}.sha1", cached.display()),
+            Algorithm::Sha1.digest(b"tampered"),
+        )
+        .unwrap();
+        fs::remove_file(&jar).unwrap();
+        let err = install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &lock,
+            false,
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch for g:a:1.0"));
+        assert!(!jar.exists());
+    }
+
+    #[test]
+    fn test_frozen_needs_a_lock_with_inputs_hash() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let lock = LockFile::parse(r#"{"version": 1, "packages": {}}"#).unwrap();
+        lock.save_to(&project.path().join("jpkg.lock")).unwrap();
+
+        let err = install_into(
+            project.path(),
+            &MemoryBackend::new(),
+            &manifest(),
+            &lock,
+            true,
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("regenerate it"), "{}", err);
+    }
+
+    #[test]
+    fn test_offline_install_needs_cached_jars() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &[])
+            .with_artifact("g:t:1.0", &[]);
+
+        let err = install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &LockFile::new(),
+            false,
+            true,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not in cache (offline mode)"));
+        assert!(!project.path().join("jpkg.lock").exists());
+    }
+
+    #[test]
+    fn test_frozen_rejects_a_changed_download() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &[])
+            .with_artifact("g:t:1.0", &[]);
+        install_into(
+            project.path(),
+            &backend,
+            &manifest(),
+            &LockFile::new(),
+            false,
+            false,
+            None,
+        )
+        .unwrap();
+        let lock = LockFile::parse(&fs::read_to_string(project.path().join("jpkg.lock")).unwrap())
+            .unwrap();
+
+        // A changed cached jar isn't copied into lib/, even with a matching .sha1
+        fs::remove_dir_all(project.path().join("lib")).unwrap();
+        let cached = cache::get_cached_jar("g", "a", "1.0", "a-1.0.jar")
+            .unwrap()
+            .unwrap();
+        fs::write(&cached, "changed").unwrap();
+        fs::write(
+            format!("{}.sha1", cached.display()),
+            Algorithm::Sha1.digest(b"changed"),
+        )
+        .unwrap();
+        let err = install_into(
+            project.path(),
+            &MemoryBackend::new(),
+            &manifest(),
+            &lock,
+            true,
+            true,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch for g:a:1.0"));
+        assert!(!project.path().join("lib/a-1.0.jar").exists());
+
+        // The repository now serves another a-1.0.jar, with a matching .sha1
+        fs::remove_dir_all(project.path().join("lib")).unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let changed = MemoryBackend::new()
+            .with_artifact("g:t:1.0", &[])
+            .with_file("g/a/1.0/a-1.0.jar", "changed")
+            .with_file("g/a/1.0/a-1.0.jar.sha1", Algorithm::Sha1.digest(b"changed"));
+        let err = install_into(
+            project.path(),
+            &changed,
+            &manifest(),
+            &lock,
+            true,
+            false,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch for g:a:1.0"));
+        assert!(!project.path().join("lib/a-1.0.jar").exists());
+        assert!(!project.path().join("lib/a-1.0.jar.part").exists());
+        assert!(
+            cache::get_cached_jar("g", "a", "1.0", "a-1.0.jar")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,67 +1,136 @@
+use crate::manifest::{ConflictStrategy, DependencySpec, Manifest, Repository, Scope};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Format of the jpkg.lock files this version writes; older ones are migrated on load
+pub const LOCK_VERSION: u64 = 2;
+
+// Maps are BTreeMaps, so the file is written in the same order every time
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LockFile {
-    pub version: String,
-    pub packages: HashMap<String, LockedPackage>,
+    #[serde(rename = "lock-version")]
+    pub lock_version: u64,
+    /// `inputs_hash` of the manifest this lock was resolved from
+    #[serde(
+        rename = "inputs-hash",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub inputs_hash: Option<String>,
+    /// Selected `group:artifact:version` of each dependency in jpkg.json
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+    pub packages: BTreeMap<String, LockedPackage>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LockedPackage {
     pub version: String,
     pub checksum: String,
+    /// Selected `group:artifact:version` of each dependency of this package
     pub dependencies: Vec<String>,
+    /// Name of the repository the artifact was fetched from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// Where the file is downloaded from (credentials redacted)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Checksum the repository publishes for the file (`sha1:<hex>` etc.),
+    /// which the download was verified against
+    #[serde(
+        rename = "repository-checksum",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub repository_checksum: Option<String>,
+    /// `<packaging>` from the POM; `pom` packages have no file
+    #[serde(default = "default_packaging")]
+    pub packaging: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier: Option<String>,
+    #[serde(default)]
+    pub scope: Scope,
+}
+
+fn default_packaging() -> String {
+    "jar".to_string()
 }
 
 impl LockFile {
     pub fn new() -> Self {
         Self {
-            version: "1".to_string(),
-            packages: HashMap::new(),
+            lock_version: LOCK_VERSION,
+            inputs_hash: None,
+            dependencies: Vec::new(),
+            packages: BTreeMap::new(),
         }
     }
 
-    #[allow(dead_code)]
     pub fn load() -> Result<Self> {
         if !Path::new("jpkg.lock").exists() {
             return Ok(Self::new());
         }
         let content = fs::read_to_string("jpkg.lock")?;
-        Ok(serde_json::from_str(&content)?)
+        Self::parse(&content)
     }
 
-    pub fn save(&self) -> Result<()> {
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write("jpkg.lock", content)?;
+    /// Read a lock of any `lock-version` up to `LOCK_VERSION`
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut value: Value = serde_json::from_str(content)?;
+        let lock_version = match value.get("lock-version") {
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("Invalid lock-version in jpkg.lock: {}", version))?,
+            // Before lock-version there was `"version": "1"`
+            None => 1,
+        };
+        if lock_version > LOCK_VERSION {
+            anyhow::bail!(
+                "jpkg.lock has lock-version {}, but this jpkg only reads up to {}; upgrade jpkg",
+                lock_version,
+                LOCK_VERSION
+            );
+        }
+        if lock_version < 2 {
+            migrate_v1(&mut value);
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(path, content)?;
         Ok(())
     }
 
+    /// Resolved from the same inputs, and every package of the graph is there
+    pub fn is_current(&self, inputs_hash: &str) -> bool {
+        self.inputs_hash.as_deref() == Some(inputs_hash)
+            && self
+                .dependencies
+                .iter()
+                .chain(self.packages.values().flat_map(|p| &p.dependencies))
+                .all(|key| self.packages.contains_key(key))
+    }
+
+    /// Record `package`; its checksum is taken from `jar_path`, if the file exists
     pub fn add_package(
         &mut self,
         key: String,
-        version: String,
-        jar_path: &Path,
-        dependencies: Vec<String>,
+        jar_path: Option<&Path>,
+        mut package: LockedPackage,
     ) -> Result<()> {
-        let checksum = if jar_path.exists() {
-            calculate_sha256(jar_path)?
-        } else {
-            String::new()
+        package.checksum = match jar_path {
+            Some(path) if path.exists() => calculate_sha256(path)?,
+            _ => String::new(),
         };
-
-        self.packages.insert(
-            key,
-            LockedPackage {
-                version,
-                checksum,
-                dependencies,
-            },
-        );
+        self.packages.insert(key, package);
         Ok(())
     }
 
@@ -70,22 +139,70 @@ impl LockFile {
         self.packages.get(key).map(|p| p.version.as_str())
     }
 
-    pub fn verify_package(&self, key: &str, jar_path: &Path) -> Result<bool> {
-        if let Some(locked) = self.packages.get(key) {
-            if locked.checksum.is_empty() {
-                // No checksum to verify
-                return Ok(true);
-            }
-            if jar_path.exists() {
-                let checksum = calculate_sha256(jar_path)?;
-                Ok(checksum == locked.checksum)
-            } else {
-                Ok(false)
-            }
-        } else {
-            Ok(false)
-        }
+    /// Check the file installed for `key` against its locked checksum
+    pub fn verify_package(&self, key: &str, jar_path: &Path) -> Result<()> {
+        let Some(locked) = self.packages.get(key) else {
+            anyhow::bail!("{} is not in jpkg.lock", key);
+        };
+        verify_sha256(
+            key,
+            &locked.checksum,
+            &fs::read(jar_path)?,
+            &jar_path.display().to_string(),
+        )
+    }
+}
+
+/// Check `bytes` of the file of `key`, which `source` names, against `checksum`,
+/// its SHA-256 in jpkg.lock
+pub fn verify_sha256(key: &str, checksum: &str, bytes: &[u8], source: &str) -> Result<()> {
+    if checksum.is_empty() {
+        anyhow::bail!("jpkg.lock has no checksum for {}", key);
+    }
+    let actual = hex::encode(Sha256::digest(bytes));
+    if actual != checksum {
+        anyhow::bail!(
+            "Checksum mismatch for {}: jpkg.lock has {}, {} has {}",
+            key,
+            checksum,
+            source,
+            actual
+        );
+    }
+    Ok(())
+}
+
+/// Version 1 only differs by its `version` field; the graph, origin, packaging
+/// and scope fields it lacks all have defaults
+fn migrate_v1(value: &mut Value) {
+    if let Some(lock) = value.as_object_mut() {
+        lock.remove("version");
+        lock.insert("lock-version".to_string(), Value::from(2));
+    }
+}
+
+/// Hash of everything resolution depends on; a lock stays current while it's unchanged
+pub fn inputs_hash(manifest: &Manifest, strategy: ConflictStrategy) -> Result<String> {
+    #[derive(Serialize)]
+    struct Inputs<'a> {
+        dependencies: &'a BTreeMap<String, DependencySpec>,
+        test_dependencies: &'a BTreeMap<String, DependencySpec>,
+        provided_dependencies: &'a BTreeMap<String, DependencySpec>,
+        repositories: &'a [Repository],
+        boms: &'a [String],
+        conflict_strategy: ConflictStrategy,
     }
+
+    let inputs = Inputs {
+        dependencies: &manifest.dependencies,
+        test_dependencies: &manifest.test_dependencies,
+        provided_dependencies: &manifest.provided_dependencies,
+        repositories: &manifest.repositories,
+        boms: &manifest.boms,
+        conflict_strategy: strategy,
+    };
+    let bytes = serde_json::to_vec(&inputs)?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
 }
 
 fn calculate_sha256(path: &Path) -> Result<String> {
@@ -95,3 +212,160 @@ fn calculate_sha256(path: &Path) -> Result<String> {
     let result = hasher.finalize();
     Ok(hex::encode(result))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v1_lockfile() {
+        let lock = LockFile::parse(
+            r#"{
+                "version": "1",
+                "packages": {
+                    "org.json:json:20240303": {
+                        "version": "20240303",
+                        "checksum": "abc",
+                        "dependencies": []
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(lock.lock_version, LOCK_VERSION);
+        let package = &lock.packages["org.json:json:20240303"];
+        assert_eq!(package.version, "20240303");
+        assert_eq!(package.packaging, "jar");
+        assert_eq!(package.scope, Scope::Compile);
+        assert!(package.url.is_none());
+        assert!(lock.dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_newer_lockfile_is_rejected() {
+        let err = LockFile::parse(r#"{"lock-version": 99, "packages": {}}"#).unwrap_err();
+        assert!(err.to_string().contains("lock-version 99"));
+    }
+
+    #[test]
+    fn test_lockfile_is_written_in_order() {
+        let package: LockedPackage =
+            serde_json::from_str(r#"{"version": "1.0", "checksum": "", "dependencies": []}"#)
+                .unwrap();
+        let mut lock = LockFile::new();
+        for key in ["org.b:b:1.0", "com.c:c:1.0", "org.a:a:1.0"] {
+            lock.packages.insert(key.to_string(), package.clone());
+        }
+        let content = serde_json::to_string_pretty(&lock).unwrap();
+        let positions: Vec<usize> = ["com.c:c:1.0", "org.a:a:1.0", "org.b:b:1.0"]
+            .iter()
+            .map(|key| content.find(key).unwrap())
+            .collect();
+        assert!(positions.is_sorted());
+        assert!(content.starts_with("{\n  \"lock-version\": 2,"));
+
+        // Round trip
+        let parsed = LockFile::parse(&content).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&parsed).unwrap(), content);
+    }
+
+    #[test]
+    fn test_inputs_hash() {
+        let mut manifest = Manifest::new("app", "1.0");
+        manifest.add_dependency("org.json:json", "20240303");
+        manifest.add_dependency("com.google.guava:guava", "33.0.0-jre");
+        let hash = inputs_hash(&manifest, ConflictStrategy::Nearest).unwrap();
+
+        // Same inputs, different order and unrelated fields
+        let mut same = Manifest::new("renamed", "2.0");
+        same.add_dependency("com.google.guava:guava", "33.0.0-jre");
+        same.add_dependency("org.json:json", "20240303");
+        assert_eq!(inputs_hash(&same, ConflictStrategy::Nearest).unwrap(), hash);
+
+        assert_ne!(
+            inputs_hash(&manifest, ConflictStrategy::Highest).unwrap(),
+            hash
+        );
+        manifest.add_dependency("org.json:json", "20231013");
+        assert_ne!(
+            inputs_hash(&manifest, ConflictStrategy::Nearest).unwrap(),
+            hash
+        );
+    }
+
+    #[test]
+    fn test_lock_is_current() {
+        let mut lock = LockFile::new();
+        lock.inputs_hash = Some("abc".to_string());
+        lock.dependencies = vec!["g:a:1.0".to_string()];
+        assert!(!lock.is_current("abc"));
+
+        lock.packages.insert(
+            "g:a:1.0".to_string(),
+            serde_json::from_str(r#"{"version": "1.0", "checksum": "", "dependencies": []}"#)
+                .unwrap(),
+        );
+        assert!(lock.is_current("abc"));
+        assert!(!lock.is_current("def"));
+    }
+
+    #[test]
+    fn test_verify_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let jar = dir.path().join("a-1.0.jar");
+        fs::write(&jar, b"jar").unwrap();
+
+        let mut lock = LockFile::new();
+        let package: LockedPackage =
+            serde_json::from_str(r#"{"version": "1.0", "checksum": "", "dependencies": []}"#)
+                .unwrap();
+        lock.packages.insert("g:a:1.0".to_string(), package.clone());
+        assert!(lock.verify_package("g:a:1.0", &jar).is_err());
+
+        lock.add_package("g:a:1.0".to_string(), Some(&jar), package)
+            .unwrap();
+        assert!(lock.verify_package("g:a:1.0", &jar).is_ok());
+        assert!(lock.verify_package("g:b:1.0", &jar).is_err());
+
+        fs::write(&jar, b"tampered").unwrap();
+        let err = lock.verify_package("g:a:1.0", &jar).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch for g:a:1.0"));
+    }
+
+    #[test]
+    fn test_lockfile_records_graph() {
+        let mut lock = LockFile::new();
+        lock.dependencies = vec!["g:a:1.0".to_string()];
+        lock.add_package(
+            "g:a:1.0".to_string(),
+            None,
+            LockedPackage {
+                version: "1.0".to_string(),
+                checksum: "stale".to_string(),
+                dependencies: vec!["g:b:2.0".to_string()],
+                repository: Some("central".to_string()),
+                url: Some("https://repo1.maven.org/maven2/g/a/1.0/a-1.0-jdk8.jar".to_string()),
+                repository_checksum: Some(
+                    "sha1:a9993e364706816aba3e25717850c26c9cd0d89d".to_string(),
+                ),
+                packaging: "bundle".to_string(),
+                classifier: Some("jdk8".to_string()),
+                scope: Scope::Runtime,
+            },
+        )
+        .unwrap();
+
+        let content = serde_json::to_string(&lock).unwrap();
+        let lock: LockFile = serde_json::from_str(&content).unwrap();
+        let package = &lock.packages["g:a:1.0"];
+        assert_eq!(package.checksum, "");
+        assert_eq!(package.dependencies, vec!["g:b:2.0"]);
+        assert_eq!(package.classifier.as_deref(), Some("jdk8"));
+        assert_eq!(
+            package.repository_checksum.as_deref(),
+            Some("sha1:a9993e364706816aba3e25717850c26c9cd0d89d")
+        );
+        assert_eq!(package.scope, Scope::Runtime);
+        assert_eq!(lock.dependencies, vec!["g:a:1.0"]);
+    }
+}
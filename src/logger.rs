@@ -6,7 +6,7 @@ const LOG_FILE: &str = ".jpkg/last_error.log";
 
 pub fn log_error(error: &str) -> Result<()> {
     fs::create_dir_all(".jpkg")?;
-    fs::write(LOG_FILE, error)?;
+    fs::write(LOG_FILE, crate::credentials::redact(error))?;
     Ok(())
 }
 
@@ -1,21 +1,29 @@
+mod backend;
 mod cache;
+mod checksum;
+mod credentials;
+mod graph;
+mod installer;
 mod lockfile;
 mod logger;
 mod manifest;
 mod maven;
 mod packager;
 mod platform;
+mod pom;
 mod project;
 mod resolver;
+mod signature;
 mod testing;
 mod updater;
+mod version;
 mod watcher;
 
 use anyhow::Result;
+use backend::RepositoryBackend;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use dialoguer::{Select, theme::ColorfulTheme};
-use indicatif::{ProgressBar, ProgressStyle};
 use manifest::Manifest;
 use maven::MavenClient;
 use resolver::Resolver;
@@ -49,12 +57,32 @@ enum Commands {
     },
     /// Install dependencies from jpkg.json
     Install {
-        /// Verify checksums from lock file (fail if mismatch)
+        /// Install exactly what jpkg.lock records; fail if jpkg.json changed or a checksum differs
         #[arg(long)]
         frozen: bool,
         /// Use only cached JARs (no network)
         #[arg(long)]
         offline: bool,
+        /// Version conflict strategy (overrides jpkg.json)
+        #[arg(long, value_enum)]
+        strategy: Option<manifest::ConflictStrategy>,
+    },
+    /// Show the resolved dependency tree
+    Tree {
+        /// Maximum number of levels to show
+        #[arg(short, long)]
+        depth: Option<usize>,
+        /// Show what depends on an artifact (group:artifact), or on every leaf if none is given
+        #[arg(short, long, value_name = "ARTIFACT", num_args = 0..=1, default_missing_value = "")]
+        invert: Option<String>,
+        /// Output format
+        #[arg(short, long, value_enum, default_value = "text")]
+        format: graph::TreeFormat,
+    },
+    /// Explain which dependencies pull in an artifact
+    Why {
+        /// Artifact to explain (group:artifact)
+        artifact: String,
     },
     /// Build the project
     Build {
@@ -175,9 +203,9 @@ fn main() -> Result<()> {
             let content = fs::read_to_string("jpkg.json")?;
             let mut manifest: Manifest = serde_json::from_str(&content)?;
 
-            manifest.dependencies.insert(
-                format!("{}:{}", selected.g, selected.a),
-                selected.latest_version.clone(),
+            manifest.add_dependency(
+                &format!("{}:{}", selected.g, selected.a),
+                &selected.latest_version,
             );
 
             let content = serde_json::to_string_pretty(&manifest)?;
@@ -248,9 +276,7 @@ fn main() -> Result<()> {
                 anyhow::bail!("{}", "jpkg.json not found. Run 'jpkg init' first.".red());
             };
 
-            manifest
-                .dependencies
-                .insert(format!("{}:{}", group, artifact), version.to_string());
+            manifest.add_dependency(&format!("{}:{}", group, artifact), &version);
 
             let content = serde_json::to_string_pretty(&manifest)?;
             fs::write("jpkg.json", content)?;
@@ -261,94 +287,68 @@ fn main() -> Result<()> {
                     .bold()
             );
         }
-        Commands::Install { frozen, offline } => {
+        Commands::Install {
+            frozen,
+            offline,
+            strategy,
+        } => {
+            installer::install(*frozen, *offline, *strategy)?;
+        }
+        Commands::Tree {
+            depth,
+            invert,
+            format,
+        } => {
             if !Path::new("jpkg.json").exists() {
                 anyhow::bail!("{}", "jpkg.json not found. Run 'jpkg init' first.".red());
             }
             let content = fs::read_to_string("jpkg.json")?;
             let manifest: Manifest = serde_json::from_str(&content)?;
 
-            let client = MavenClient::new();
+            let client = installer::repository_client(&manifest, &lockfile::LockFile::load()?)?;
             let mut resolver = Resolver::new(&client, &manifest);
+            resolver.resolve()?;
+            let tree = resolver.graph().tree(*depth, invert.as_deref())?;
 
-            println!("{}", "📦 Resolving dependencies...".cyan());
-            let resolved = resolver.resolve()?;
-
-            println!(
-                "{}",
-                format!("✓ Resolved {} packages:", resolved.len()).green()
-            );
-            for pkg in &resolved {
-                println!("  {} {}", "•".blue(), pkg);
+            match format {
+                graph::TreeFormat::Text => print!("{}", graph::render_text(&tree)),
+                graph::TreeFormat::Json => println!("{}", serde_json::to_string_pretty(&tree)?),
+                graph::TreeFormat::Dot => print!("{}", graph::render_dot(&tree)),
             }
-
-            let lib_dir = Path::new("lib");
-            if !lib_dir.exists() {
-                fs::create_dir(lib_dir)?;
+        }
+        Commands::Why { artifact } => {
+            if !Path::new("jpkg.json").exists() {
+                anyhow::bail!("{}", "jpkg.json not found. Run 'jpkg init' first.".red());
             }
+            let content = fs::read_to_string("jpkg.json")?;
+            let manifest: Manifest = serde_json::from_str(&content)?;
 
-            // Initialize cache
-            cache::init_cache()?;
-
-            // Create/load lock file
-            let mut lockfile = if *frozen {
-                lockfile::LockFile::load()?
-            } else {
-                lockfile::LockFile::new()
-            };
-
-            let pb = ProgressBar::new(resolved.len() as u64);
-            pb.set_style(ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-                .unwrap()
-                .progress_chars("#>-"));
-
-            for pkg in resolved {
-                let parts: Vec<&str> = pkg.split(':').collect();
-                let group = parts[0];
-                let artifact = parts[1];
-                let version = parts[2];
-
-                let filename = format!("{}-{}.jar", artifact, version);
-                let path = lib_dir.join(filename);
-
-                // Verify checksum if frozen
-                if *frozen {
-                    if !lockfile.verify_package(&pkg, &path)? {
-                        anyhow::bail!("{}", format!("Checksum mismatch for {}", pkg).red());
-                    }
-                }
-
-                // Try cache first
-                if let Some(cached_path) = cache::get_cached_jar(group, artifact, version)? {
-                    if !path.exists() {
-                        fs::copy(&cached_path, &path)?;
-                    }
-                } else if *offline {
-                    // Offline mode: fail if not in cache
-                    anyhow::bail!(
-                        "{}",
-                        format!("Artifact {} not in cache (offline mode)", pkg).red()
-                    );
-                } else {
-                    // Download if not in cache
-                    if !path.exists() {
-                        client.download_jar(group, artifact, version, &path)?;
-                        // Cache the downloaded JAR
-                        cache::cache_jar(group, artifact, version, &path)?;
-                    }
-                }
-
-                // Add to lock file
-                lockfile.add_package(pkg.clone(), version.to_string(), &path, Vec::new())?;
+            let client = installer::repository_client(&manifest, &lockfile::LockFile::load()?)?;
+            let mut resolver = Resolver::new(&client, &manifest);
+            resolver.resolve()?;
+            let graph = resolver.graph();
+            let key = graph.selected(artifact)?;
 
-                pb.inc(1);
+            const MAX_PATHS: usize = 50;
+            let paths = graph.paths_to(key, MAX_PATHS);
+            println!(
+                "{} {} is pulled in by {} path{}:",
+                key.bold(),
+                format!("[{}]", resolver.scope(key)).dimmed(),
+                paths.len(),
+                if paths.len() == 1 { "" } else { "s" }
+            );
+            for path in &paths {
+                println!();
+                print!("{}", graph::render_path(&graph.package, path));
+            }
+            if paths.len() == MAX_PATHS {
+                println!();
+                println!(
+                    "{}",
+                    format!("Only the first {} paths are shown", MAX_PATHS).dimmed()
+                );
             }
-            pb.finish_with_message(format!("{}", "✓ Done!".green().bold()));
-
-            // Save lock file
-            lockfile.save()?;
-            println!("{}", "✓ Saved jpkg.lock".green());
         }
         Commands::Build { verbose } => {
             project::build_project(*verbose)?;
@@ -370,10 +370,14 @@ fn main() -> Result<()> {
 
             let mut key_to_remove = None;
 
-            if manifest.dependencies.contains_key(name) {
+            let declared: Vec<String> = manifest
+                .all_dependencies()
+                .map(|(key, _, _)| key.clone())
+                .collect();
+            if declared.contains(name) {
                 key_to_remove = Some(name.to_string());
             } else {
-                for (key, _) in &manifest.dependencies {
+                for key in &declared {
                     if key.ends_with(&format!(":{}", name)) || key == name {
                         key_to_remove = Some(key.clone());
                         break;
@@ -382,7 +386,7 @@ fn main() -> Result<()> {
             }
 
             if let Some(key) = key_to_remove {
-                manifest.dependencies.remove(&key);
+                manifest.remove_dependency(&key);
                 let content = serde_json::to_string_pretty(&manifest)?;
                 fs::write("jpkg.json", content)?;
                 println!(
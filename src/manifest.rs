@@ -1,11 +1,313 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Manifest {
     pub package: PackageInfo,
     #[serde(default)]
-    pub dependencies: HashMap<String, String>,
+    pub dependencies: BTreeMap<String, DependencySpec>,
+    /// Only on the test classpath
+    #[serde(
+        rename = "test-dependencies",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub test_dependencies: BTreeMap<String, DependencySpec>,
+    /// On the compile and test classpaths, but supplied at runtime by the environment
+    #[serde(
+        rename = "provided-dependencies",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub provided_dependencies: BTreeMap<String, DependencySpec>,
+    /// Maven repositories to resolve from, tried in order.
+    /// Maven Central is appended unless a repository named "central" is declared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub repositories: Vec<Repository>,
+    /// BOMs (`group:artifact:version`) whose managed versions apply to
+    /// dependencies declared without a version and to transitive dependencies
+    #[serde(default, alias = "platforms", skip_serializing_if = "Vec::is_empty")]
+    pub boms: Vec<String>,
+    /// How to pick one version when several are requested for the same artifact
+    #[serde(
+        rename = "conflict-strategy",
+        default,
+        skip_serializing_if = "ConflictStrategy::is_default"
+    )]
+    pub conflict_strategy: ConflictStrategy,
+    /// Require a valid PGP signature for every jar and POM that is downloaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signatures: Option<SignaturePolicy>,
+    /// How often failed requests are retried, and how long to wait in between
+    #[serde(default, skip_serializing_if = "RetryPolicy::is_default")]
+    pub retry: RetryPolicy,
+    /// Reuse artifacts from Maven's local repository
+    #[serde(
+        rename = "maven-local",
+        default,
+        skip_serializing_if = "MavenLocal::is_default"
+    )]
+    pub maven_local: MavenLocal,
+}
+
+/// A dependency in jpkg.json: either just a version (or range), or a table
+///
+/// ```json
+/// "org.apache.hadoop:hadoop-common": {
+///     "version": "3.3.6",
+///     "exclusions": ["log4j:log4j", "org.slf4j:*"]
+/// },
+/// "com.oracle:ojdbc": { "version": "12.1.0", "pom": false }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DependencySpec {
+    Version(String),
+    Detailed(DetailedDependency),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DetailedDependency {
+    /// Empty when a BOM manages the version
+    #[serde(default)]
+    pub version: String,
+    /// Transitive artifacts to leave out (`group:artifact`, `*` allowed)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclusions: Vec<String>,
+    /// `false` for an artifact published without a POM: its POM isn't fetched,
+    /// and it is installed without dependencies
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub pom: bool,
+}
+
+impl Default for DetailedDependency {
+    fn default() -> Self {
+        Self {
+            version: String::new(),
+            exclusions: Vec::new(),
+            pom: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+impl DependencySpec {
+    pub fn version(&self) -> &str {
+        match self {
+            DependencySpec::Version(version) => version,
+            DependencySpec::Detailed(detailed) => &detailed.version,
+        }
+    }
+
+    /// Change the version, keeping any other settings
+    pub fn set_version(&mut self, version: &str) {
+        match self {
+            DependencySpec::Version(v) => *v = version.to_string(),
+            DependencySpec::Detailed(detailed) => detailed.version = version.to_string(),
+        }
+    }
+
+    pub fn exclusions(&self) -> &[String] {
+        match self {
+            DependencySpec::Version(_) => &[],
+            DependencySpec::Detailed(detailed) => &detailed.exclusions,
+        }
+    }
+
+    /// Whether the artifact has a POM to read its dependencies from
+    pub fn has_pom(&self) -> bool {
+        match self {
+            DependencySpec::Version(_) => true,
+            DependencySpec::Detailed(detailed) => detailed.pom,
+        }
+    }
+}
+
+impl From<&str> for DependencySpec {
+    fn from(version: &str) -> Self {
+        DependencySpec::Version(version.to_string())
+    }
+}
+
+/// Which classpaths a dependency belongs on
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Compile, runtime and test classpaths
+    #[default]
+    Compile,
+    /// Runtime and test classpaths
+    Runtime,
+    /// Compile and test classpaths; left out of the packaged jar
+    Provided,
+    /// Test classpath only
+    Test,
+}
+
+impl Scope {
+    /// Parse a POM `<scope>`; `None` for scopes that are never resolved (system, import)
+    pub fn from_pom(scope: Option<&str>) -> Option<Self> {
+        match scope.map(str::trim) {
+            None | Some("") | Some("compile") => Some(Scope::Compile),
+            Some("runtime") => Some(Scope::Runtime),
+            Some("provided") => Some(Scope::Provided),
+            Some("test") => Some(Scope::Test),
+            _ => None,
+        }
+    }
+
+    /// Scope of a dependency declared with `declared` by an artifact in this scope,
+    /// or `None` if it isn't inherited (Maven's scope table)
+    pub fn transitive(self, declared: Scope) -> Option<Self> {
+        match (self, declared) {
+            (_, Scope::Provided | Scope::Test) => None,
+            (Scope::Compile, declared) => Some(declared),
+            (scope, _) => Some(scope),
+        }
+    }
+
+    /// Scope of an artifact needed both in this scope and in `other`
+    pub fn widen(self, other: Scope) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Scope::Test, other) | (other, Scope::Test) => other,
+            // Compile, or needed at compile time by one path and at runtime by another
+            _ => Scope::Compile,
+        }
+    }
+
+    /// Directory the jars of this scope are installed to
+    pub fn lib_dir(self) -> &'static str {
+        match self {
+            Scope::Compile => "lib",
+            Scope::Runtime => "lib/runtime",
+            Scope::Provided => "lib/provided",
+            Scope::Test => "lib/test",
+        }
+    }
+
+    pub const ALL: [Scope; 4] = [Scope::Compile, Scope::Runtime, Scope::Provided, Scope::Test];
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Compile => write!(f, "compile"),
+            Scope::Runtime => write!(f, "runtime"),
+            Scope::Provided => write!(f, "provided"),
+            Scope::Test => write!(f, "test"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictStrategy {
+    /// The version requested closest to jpkg.json wins (Maven)
+    #[default]
+    Nearest,
+    /// The highest requested version wins (Gradle)
+    Highest,
+}
+
+impl ConflictStrategy {
+    fn is_default(&self) -> bool {
+        *self == ConflictStrategy::default()
+    }
+}
+
+/// `maven-local` in jpkg.json. Jars and POMs missing from jpkg's cache are looked
+/// up in Maven's local repository (`localRepository` from ~/.m2/settings.xml, or
+/// ~/.m2/repository) before they're downloaded. Its jars often have no checksum
+/// files to check them against, so it is only used if jpkg.json has the section.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MavenLocal {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Use this directory instead of the one Maven is configured with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Only read from it; with `false`, downloaded jars and POMs are added to it too
+    #[serde(rename = "read-only", default = "default_true")]
+    pub read_only: bool,
+}
+
+impl Default for MavenLocal {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            read_only: true,
+        }
+    }
+}
+
+impl MavenLocal {
+    fn is_default(&self) -> bool {
+        *self == MavenLocal::default()
+    }
+}
+
+/// `retry` in jpkg.json. Timeouts, connection errors, 408, 429 and 5xx responses are
+/// retried; the delay doubles after every attempt.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Delay before the first retry, in milliseconds
+    #[serde(rename = "backoff-ms", default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_backoff_ms() -> u64 {
+    500
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: default_retries(),
+            backoff_ms: default_backoff_ms(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Longest wait between two attempts
+    const MAX_DELAY: Duration = Duration::from_secs(30);
+
+    fn is_default(&self) -> bool {
+        *self == RetryPolicy::default()
+    }
+
+    /// How long to wait before retry number `retry` (counting from 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        Duration::from_millis(self.backoff_ms.saturating_mul(factor)).min(Self::MAX_DELAY)
+    }
+}
+
+impl std::fmt::Display for ConflictStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictStrategy::Nearest => write!(f, "nearest wins"),
+            ConflictStrategy::Highest => write!(f, "highest wins"),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -15,6 +317,39 @@ pub struct PackageInfo {
     pub description: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Repository {
+    #[serde(alias = "id")]
+    pub name: String,
+    /// `https://` URL, or a local directory laid out the same way: a `file://`
+    /// URL or a path (relative to the project)
+    pub url: String,
+    /// Group ids served by this repository ("com.mycorp" or "com.mycorp.*").
+    /// An empty list means the repository serves every artifact.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<String>,
+}
+
+/// `signatures` in jpkg.json
+///
+/// ```json
+/// "signatures": {
+///     "keyring": "~/.jpkg/gnupg",
+///     "keys": { "com.google.*": ["EE0CA873074092F806F59B65D364ABAA39A47320"] }
+/// }
+/// ```
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct SignaturePolicy {
+    /// GnuPG home directory holding the trusted public keys; gpg's own if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring: Option<String>,
+    /// Fingerprints of the (primary) keys allowed to sign each group
+    /// ("com.mycorp" or "com.mycorp.*"). Other groups may be signed by any
+    /// key in the keyring.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
 impl Manifest {
     pub fn new(name: &str, version: &str) -> Self {
         Manifest {
@@ -23,7 +358,333 @@ impl Manifest {
                 version: version.to_string(),
                 description: None,
             },
-            dependencies: HashMap::new(),
+            dependencies: BTreeMap::new(),
+            test_dependencies: BTreeMap::new(),
+            provided_dependencies: BTreeMap::new(),
+            repositories: Vec::new(),
+            boms: Vec::new(),
+            conflict_strategy: ConflictStrategy::default(),
+            signatures: None,
+            retry: RetryPolicy::default(),
+            maven_local: MavenLocal::default(),
+        }
+    }
+
+    /// Direct dependencies of every section, with the scope each section implies
+    pub fn all_dependencies(&self) -> impl Iterator<Item = (&String, &DependencySpec, Scope)> {
+        fn section(
+            deps: &BTreeMap<String, DependencySpec>,
+            scope: Scope,
+        ) -> impl Iterator<Item = (&String, &DependencySpec, Scope)> {
+            deps.iter().map(move |(name, spec)| (name, spec, scope))
+        }
+        section(&self.dependencies, Scope::Compile)
+            .chain(section(&self.provided_dependencies, Scope::Provided))
+            .chain(section(&self.test_dependencies, Scope::Test))
+    }
+
+    /// The declaration of `artifact`, whichever section it's in
+    pub fn dependency_mut(&mut self, artifact: &str) -> Option<&mut DependencySpec> {
+        [
+            &mut self.dependencies,
+            &mut self.provided_dependencies,
+            &mut self.test_dependencies,
+        ]
+        .into_iter()
+        .find_map(|deps| deps.get_mut(artifact))
+    }
+
+    /// Add `artifact` at `version`, or change its version if it's already declared
+    pub fn add_dependency(&mut self, artifact: &str, version: &str) {
+        match self.dependency_mut(artifact) {
+            Some(spec) => spec.set_version(version),
+            None => {
+                self.dependencies
+                    .insert(artifact.to_string(), version.into());
+            }
+        }
+    }
+
+    /// Remove `artifact` from every section; false if it wasn't declared
+    pub fn remove_dependency(&mut self, artifact: &str) -> bool {
+        let mut removed = false;
+        for deps in [
+            &mut self.dependencies,
+            &mut self.provided_dependencies,
+            &mut self.test_dependencies,
+        ] {
+            removed |= deps.remove(artifact).is_some();
+        }
+        removed
+    }
+}
+
+impl Repository {
+    /// Check whether this repository should be asked for artifacts of `group`
+    pub fn serves(&self, group: &str) -> bool {
+        self.groups.is_empty()
+            || self
+                .groups
+                .iter()
+                .any(|pattern| group_matches(pattern, group))
+    }
+
+    /// The directory of a repository that is on the local filesystem
+    pub fn local_path(&self) -> Option<PathBuf> {
+        match self.url.strip_prefix("file://") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => (!self.url.contains("://")).then(|| PathBuf::from(&self.url)),
+        }
+    }
+}
+
+impl SignaturePolicy {
+    /// Fingerprints pinned for `group`, or `None` if no pattern matches it
+    pub fn pinned_keys(&self, group: &str) -> Option<Vec<&str>> {
+        let mut pinned = self
+            .keys
+            .iter()
+            .filter(|(pattern, _)| group_matches(pattern, group))
+            .flat_map(|(_, keys)| keys.iter().map(String::as_str))
+            .peekable();
+        pinned.peek()?;
+        Some(pinned.collect())
+    }
+}
+
+/// `com.mycorp` matches only that group, `com.mycorp.*` also its sub-groups
+fn group_matches(pattern: &str, group: &str) -> bool {
+    let prefix = pattern.trim_end_matches(".*").trim_end_matches('*');
+    group == prefix
+        || (pattern.ends_with('*')
+            && group.starts_with(prefix)
+            && (prefix.ends_with('.') || group[prefix.len()..].starts_with('.')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(groups: &[&str]) -> Repository {
+        Repository {
+            name: "internal".to_string(),
+            url: "https://nexus.example.com/repository/maven".to_string(),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
         }
     }
+
+    #[test]
+    fn test_repository_serves_everything_without_groups() {
+        assert!(repo(&[]).serves("com.google.guava"));
+    }
+
+    #[test]
+    fn test_local_repositories() {
+        let mut repository = repo(&[]);
+        assert_eq!(repository.local_path(), None);
+
+        repository.url = "file:///srv/maven".to_string();
+        assert_eq!(repository.local_path(), Some(PathBuf::from("/srv/maven")));
+        repository.url = "tests/fixtures/repo".to_string();
+        assert_eq!(
+            repository.local_path(),
+            Some(PathBuf::from("tests/fixtures/repo"))
+        );
+    }
+
+    #[test]
+    fn test_repository_group_patterns() {
+        let exact = repo(&["com.mycorp"]);
+        assert!(exact.serves("com.mycorp"));
+        assert!(!exact.serves("com.mycorp.util"));
+
+        let wildcard = repo(&["com.mycorp.*"]);
+        assert!(wildcard.serves("com.mycorp"));
+        assert!(wildcard.serves("com.mycorp.util"));
+        assert!(!wildcard.serves("com.mycorporation"));
+        assert!(!wildcard.serves("org.apache"));
+    }
+
+    #[test]
+    fn test_repositories_are_optional_in_manifest() {
+        let manifest: Manifest =
+            serde_json::from_str(r#"{"package": {"name": "a", "version": "1"}}"#).unwrap();
+        assert!(manifest.repositories.is_empty());
+
+        let content = serde_json::to_string(&manifest).unwrap();
+        assert!(!content.contains("repositories"));
+    }
+
+    #[test]
+    fn test_dependency_specs() {
+        let mut manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "a", "version": "1"},
+                "dependencies": {
+                    "org.json:json": "20240303",
+                    "org.apache.hadoop:hadoop-common": {
+                        "version": "3.3.6",
+                        "exclusions": ["log4j:log4j", "org.slf4j:*"]
+                    },
+                    "com.oracle:ojdbc": {"version": "12.1.0", "pom": false}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let json = &manifest.dependencies["org.json:json"];
+        assert_eq!(json.version(), "20240303");
+        assert!(json.exclusions().is_empty());
+
+        let hadoop = &manifest.dependencies["org.apache.hadoop:hadoop-common"];
+        assert_eq!(hadoop.version(), "3.3.6");
+        assert_eq!(hadoop.exclusions(), ["log4j:log4j", "org.slf4j:*"]);
+        assert!(json.has_pom() && hadoop.has_pom());
+        assert!(!manifest.dependencies["com.oracle:ojdbc"].has_pom());
+
+        manifest.add_dependency("org.apache.hadoop:hadoop-common", "3.4.0");
+        let hadoop = &manifest.dependencies["org.apache.hadoop:hadoop-common"];
+        assert_eq!(hadoop.version(), "3.4.0");
+        assert_eq!(hadoop.exclusions().len(), 2);
+
+        let content = serde_json::to_string(&manifest).unwrap();
+        assert!(content.contains(r#""org.json:json":"20240303""#));
+        assert!(content.contains(r#""pom":false"#));
+        assert_eq!(content.matches(r#""pom""#).count(), 1);
+    }
+
+    #[test]
+    fn test_dependency_sections() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "a", "version": "1"},
+                "dependencies": {"org.json:json": "20240303"},
+                "test-dependencies": {"junit:junit": "4.13.2"},
+                "provided-dependencies": {"jakarta.servlet:jakarta.servlet-api": "6.0.0"}
+            }"#,
+        )
+        .unwrap();
+        let mut scopes: Vec<_> = manifest
+            .all_dependencies()
+            .map(|(name, _, scope)| (name.as_str(), scope))
+            .collect();
+        scopes.sort_by_key(|(name, _)| *name);
+        assert_eq!(
+            scopes,
+            vec![
+                ("jakarta.servlet:jakarta.servlet-api", Scope::Provided),
+                ("junit:junit", Scope::Test),
+                ("org.json:json", Scope::Compile),
+            ]
+        );
+
+        let content = serde_json::to_string(&Manifest::new("a", "1")).unwrap();
+        assert!(!content.contains("test-dependencies"));
+
+        // jpkg.json keeps its dependencies sorted, whatever order they were added in
+        let mut manifest = Manifest::new("a", "1");
+        manifest.add_dependency("org.b:b", "1.0");
+        manifest.add_dependency("com.a:a", "1.0");
+        let content = serde_json::to_string(&manifest).unwrap();
+        assert!(content.find("com.a:a") < content.find("org.b:b"));
+    }
+
+    #[test]
+    fn test_scope_propagation() {
+        assert_eq!(
+            Scope::Compile.transitive(Scope::Runtime),
+            Some(Scope::Runtime)
+        );
+        assert_eq!(Scope::Test.transitive(Scope::Compile), Some(Scope::Test));
+        assert_eq!(
+            Scope::Provided.transitive(Scope::Runtime),
+            Some(Scope::Provided)
+        );
+        assert_eq!(Scope::Compile.transitive(Scope::Provided), None);
+        assert_eq!(Scope::Compile.transitive(Scope::Test), None);
+
+        assert_eq!(Scope::Test.widen(Scope::Runtime), Scope::Runtime);
+        assert_eq!(Scope::Provided.widen(Scope::Runtime), Scope::Compile);
+        assert_eq!(Scope::Provided.widen(Scope::Provided), Scope::Provided);
+        assert_eq!(Scope::from_pom(None), Some(Scope::Compile));
+        assert_eq!(Scope::from_pom(Some("system")), None);
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{"package": {"name": "a", "version": "1"}, "retry": {"retries": 5}}"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.retry.retries, 5);
+        assert_eq!(manifest.retry.backoff_ms, 500);
+
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.delay(1), Duration::from_millis(500));
+        assert_eq!(retry.delay(3), Duration::from_millis(2000));
+        assert_eq!(retry.delay(40), Duration::from_secs(30));
+
+        let content = serde_json::to_string(&Manifest::new("a", "1")).unwrap();
+        assert!(!content.contains("retry"));
+    }
+
+    #[test]
+    fn test_maven_local() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{"package": {"name": "a", "version": "1"}, "maven-local": {"read-only": false}}"#,
+        )
+        .unwrap();
+        assert!(manifest.maven_local.enabled);
+        assert!(!manifest.maven_local.read_only);
+        assert_eq!(manifest.maven_local.path, None);
+
+        let manifest = Manifest::new("a", "1");
+        assert!(!manifest.maven_local.enabled);
+        let content = serde_json::to_string(&manifest).unwrap();
+        assert!(!content.contains("maven-local"));
+    }
+
+    #[test]
+    fn test_signature_policy() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "a", "version": "1"},
+                "signatures": {
+                    "keys": {
+                        "com.google.*": ["AAAA"],
+                        "com.google.guava": ["BBBB"]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let policy = manifest.signatures.unwrap();
+        assert_eq!(policy.keyring, None);
+        assert_eq!(
+            policy.pinned_keys("com.google.guava"),
+            Some(vec!["AAAA", "BBBB"])
+        );
+        assert_eq!(
+            policy.pinned_keys("com.google.code.gson"),
+            Some(vec!["AAAA"])
+        );
+        assert_eq!(policy.pinned_keys("org.json"), None);
+
+        let content = serde_json::to_string(&Manifest::new("a", "1")).unwrap();
+        assert!(!content.contains("signatures"));
+    }
+
+    #[test]
+    fn test_conflict_strategy() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{"package": {"name": "a", "version": "1"}, "conflict-strategy": "highest"}"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.conflict_strategy, ConflictStrategy::Highest);
+
+        let manifest = Manifest::new("a", "1");
+        assert_eq!(manifest.conflict_strategy, ConflictStrategy::Nearest);
+        let content = serde_json::to_string(&manifest).unwrap();
+        assert!(!content.contains("conflict-strategy"));
+    }
 }
@@ -1,95 +1,808 @@
+use crate::backend::{DirectoryBackend, RepositoryBackend, RepositoryFiles};
+use crate::cache;
+use crate::checksum::{self, Checksum};
+use crate::credentials::{self, Credentials};
+use crate::lockfile;
+use crate::manifest::{Repository, RetryPolicy, SignaturePolicy};
+use crate::signature::{Signature, SignatureError, Verifier};
+use crate::version;
 use anyhow::{Context, Result};
-use reqwest::blocking::Client;
-use serde::Deserialize;
+use futures::stream::{self, StreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::runtime::Runtime;
 
-const MAVEN_CENTRAL: &str = "https://repo1.maven.org/maven2";
+pub const MAVEN_CENTRAL: &str = "https://repo1.maven.org/maven2";
 
+/// Requests in flight at once, for POMs during resolution and for downloads
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// The HTTP `RepositoryBackend`: talks to Maven repositories. Requests are
+/// async; the methods block on the client's own runtime, and the batch ones
+/// (`get_poms`, `download_all`) run their requests concurrently.
 pub struct MavenClient {
     client: Client,
-    base_url: String,
+    runtime: Runtime,
+    repositories: Vec<Repository>,
+    /// Credentials keyed by repository name
+    credentials: HashMap<String, Credentials>,
+    /// Repository that served each artifact (`group:artifact:version` -> repository name)
+    origins: RefCell<HashMap<String, String>>,
+    /// Checks the signatures of downloaded jars and POMs, if jpkg.json asks for it
+    signatures: Option<Verifier>,
+    retry: RetryPolicy,
+    /// Only read POMs and metadata from the cache
+    offline: bool,
+}
+
+/// A file for `RepositoryBackend::download_all` to fetch (see `file_name`)
+pub struct DownloadRequest {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+    pub file_name: String,
+    pub output_path: PathBuf,
+    /// SHA-256 the file must have, from jpkg.lock (when the lock is current)
+    pub locked_checksum: Option<String>,
+}
+
+impl DownloadRequest {
+    /// Check `bytes` of the file, fetched from `source`, against its locked checksum
+    pub fn verify_locked(&self, bytes: &[u8], source: &str) -> Result<()> {
+        let Some(checksum) = &self.locked_checksum else {
+            return Ok(());
+        };
+        let key = format!("{}:{}:{}", self.group_id, self.artifact_id, self.version);
+        lockfile::verify_sha256(&key, checksum, bytes, source)
+    }
+}
+
+/// A file downloaded by `RepositoryBackend::download_all`, after it passed every check
+pub struct Download {
+    /// Checksum the repository publishes for the file, if any
+    pub checksum: Option<Checksum>,
+    /// Its signature, if signatures are verified
+    pub signature: Option<Signature>,
+}
+
+/// A file as a repository served it
+enum Body {
+    Http(Response),
+    /// Read from a repository on the local filesystem
+    Local(Vec<u8>),
+}
+
+impl Body {
+    async fn bytes(self) -> Result<Vec<u8>> {
+        match self {
+            Body::Http(response) => Ok(response.bytes().await?.to_vec()),
+            Body::Local(bytes) => Ok(bytes),
+        }
+    }
+
+    async fn text(self) -> Result<String> {
+        match self {
+            Body::Http(response) => Ok(response.text().await?),
+            Body::Local(bytes) => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        }
+    }
 }
 
 impl MavenClient {
     pub fn new() -> Self {
+        Self::with_repositories(&[])
+    }
+
+    /// Create a client that tries `repositories` in order, falling back to Maven Central
+    pub fn with_repositories(repositories: &[Repository]) -> Self {
+        let mut repositories = repositories.to_vec();
+        if !repositories.iter().any(|r| r.name == "central") {
+            repositories.push(Repository {
+                name: "central".to_string(),
+                url: MAVEN_CENTRAL.to_string(),
+                groups: Vec::new(),
+            });
+        }
+
         Self {
+            // No overall timeout: a large jar may take long, as long as data keeps coming
             client: Client::builder()
-                .timeout(std::time::Duration::from_secs(30))
+                .connect_timeout(std::time::Duration::from_secs(30))
+                .read_timeout(std::time::Duration::from_secs(30))
+                .build()
+                .unwrap(),
+            runtime: tokio::runtime::Builder::new_current_thread()
+                .enable_all()
                 .build()
                 .unwrap(),
-            base_url: MAVEN_CENTRAL.to_string(),
+            repositories,
+            credentials: HashMap::new(),
+            origins: RefCell::new(HashMap::new()),
+            signatures: None,
+            retry: RetryPolicy::default(),
+            offline: false,
         }
     }
 
-    #[allow(dead_code)]
-    pub fn get_metadata(&self, group_id: &str, artifact_id: &str) -> Result<MavenMetadata> {
-        let url = format!(
-            "{}/{}/{}/maven-metadata.xml",
-            self.base_url,
+    /// Authenticate requests to repositories that have an entry in `credentials`
+    pub fn with_credentials(mut self, credentials: HashMap<String, Credentials>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Verify signatures according to `policy`
+    pub fn with_signatures(mut self, policy: Option<SignaturePolicy>) -> Self {
+        self.signatures = policy.map(Verifier::new);
+        self
+    }
+
+    /// Retry failed requests according to `retry`
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Never go to the network for POMs and metadata; they must be in the cache
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Try `repository` first for `coordinate` (e.g. because it served it last time)
+    pub fn prefer(&self, coordinate: &str, repository: &str) {
+        self.origins
+            .borrow_mut()
+            .insert(coordinate.to_string(), repository.to_string());
+    }
+
+    /// Repositories serving `group`, with the preferred one for `coordinate` first
+    fn repositories_for(&self, group: &str, coordinate: Option<&str>) -> Vec<&Repository> {
+        let mut repos: Vec<&Repository> = self
+            .repositories
+            .iter()
+            .filter(|r| r.serves(group))
+            .collect();
+
+        if let Some(preferred) = coordinate.and_then(|c| self.origin(c))
+            && let Some(pos) = repos.iter().position(|r| r.name == preferred)
+        {
+            let repo = repos.remove(pos);
+            repos.insert(0, repo);
+        }
+        repos
+    }
+
+    /// Get `path` from the first repository that has it, from byte `offset` on
+    /// (see `fetch_from`)
+    async fn fetch(
+        &self,
+        group: &str,
+        coordinate: Option<&str>,
+        path: &str,
+        offset: u64,
+    ) -> Result<(&Repository, Body)> {
+        let mut tried = Vec::new();
+        for repo in self.repositories_for(group, coordinate) {
+            match self.fetch_from(repo, path, offset).await {
+                Ok(body) => {
+                    if let Some(coordinate) = coordinate {
+                        self.prefer(coordinate, &repo.name);
+                    }
+                    return Ok((repo, body));
+                }
+                Err(reason) => tried.push(reason),
+            }
+        }
+
+        if tried.is_empty() {
+            anyhow::bail!("No repository configured for group '{}'", group);
+        }
+        anyhow::bail!(
+            "Could not fetch {} from any repository:\n  {}",
+            path,
+            tried.join("\n  ")
+        )
+    }
+
+    /// Get `path` from `repo`, from byte `offset` on if it's served over HTTP;
+    /// on failure, the URL tried and what went wrong
+    async fn fetch_from(&self, repo: &Repository, path: &str, offset: u64) -> Result<Body, String> {
+        let shown_url = shown_url(repo, path);
+        match directory(repo) {
+            Some(directory) => match directory.read(path) {
+                Ok(Some(bytes)) => Ok(Body::Local(bytes)),
+                Ok(None) => Err(format!("{} (not found)", shown_url)),
+                Err(e) => Err(format!("{} ({:#})", shown_url, e)),
+            },
+            None => match self.get_from(repo, path, offset).await {
+                // The caller deals with a range that doesn't fit the file
+                Ok(response)
+                    if response.status().is_success()
+                        || response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+                            && offset > 0 =>
+                {
+                    Ok(Body::Http(response))
+                }
+                Ok(response) if matches!(response.status().as_u16(), 401 | 403) => Err(format!(
+                    "{} ({}, check credentials for '{}' in ~/.jpkg/credentials)",
+                    shown_url,
+                    response.status(),
+                    repo.name
+                )),
+                Ok(response) => Err(format!("{} ({})", shown_url, response.status())),
+                Err(e) => Err(format!("{} ({})", shown_url, e.without_url())),
+            },
+        }
+    }
+
+    /// The versions `repo` publishes of an artifact. Directory repositories
+    /// need no maven-metadata.xml; their version directories are listed instead.
+    async fn metadata_from(
+        &self,
+        repo: &Repository,
+        group_id: &str,
+        artifact_id: &str,
+    ) -> Result<MavenMetadata, String> {
+        let path = format!(
+            "{}/{}/maven-metadata.xml",
             group_id.replace('.', "/"),
             artifact_id
         );
+        let shown_url = shown_url(repo, &path);
+        if let Some(directory) = directory(repo) {
+            return directory
+                .get_metadata(group_id, artifact_id)
+                .map_err(|e| format!("{} ({:#})", shown_url, e));
+        }
+        let text = self
+            .fetch_from(repo, &path, 0)
+            .await?
+            .text()
+            .await
+            .map_err(|e| format!("{} ({:#})", shown_url, e))?;
+        quick_xml::de::from_str(&text).map_err(|e| format!("{} ({})", shown_url, e))
+    }
 
-        let response = self.client.get(&url).send()?.text()?;
-        let metadata: MavenMetadata = quick_xml::de::from_str(&response)?;
-        Ok(metadata)
+    /// GET `path` from `repo`, authenticated if there are credentials for it
+    async fn get(&self, repo: &Repository, path: &str) -> reqwest::Result<Response> {
+        self.get_from(repo, path, 0).await
     }
 
-    pub fn get_pom(&self, group_id: &str, artifact_id: &str, version: &str) -> Result<Project> {
-        let url = format!(
-            "{}/{}/{}/{}/{}-{}.pom",
-            self.base_url,
-            group_id.replace('.', "/"),
-            artifact_id,
-            version,
-            artifact_id,
-            version
-        );
-        let response = self.client.get(&url).send()?.text()?;
-        let project: Project = quick_xml::de::from_str(&response)?;
+    /// GET `path` from `repo` from byte `offset` on. Timeouts, dropped connections
+    /// and overloaded servers are retried with exponential backoff.
+    async fn get_from(
+        &self,
+        repo: &Repository,
+        path: &str,
+        offset: u64,
+    ) -> reqwest::Result<Response> {
+        let url = format!("{}/{}", repo.url.trim_end_matches('/'), path);
+        let mut retry = 0;
+        loop {
+            let mut request = self.client.get(&url);
+            if let Some(creds) = self.credentials.get(&repo.name) {
+                request = creds.apply(request);
+            }
+            if offset > 0 {
+                request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+            }
+
+            let response = request.send().await;
+            let transient = match &response {
+                Ok(response) => is_transient(response.status()),
+                Err(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            };
+            if !transient || retry >= self.retry.retries {
+                return response;
+            }
+            retry += 1;
+            tokio::time::sleep(self.retry.delay(retry)).await;
+        }
+    }
+
+    /// The POM of an artifact, from the cache if it's there. Fetched POMs are
+    /// cached once verified.
+    async fn pom(&self, group_id: &str, artifact_id: &str, version: &str) -> Result<Project> {
+        let coordinate = format!("{}:{}:{}", group_id, artifact_id, version);
+        if let Some(text) = self.cached_pom(group_id, artifact_id, version)? {
+            return quick_xml::de::from_str(&text)
+                .with_context(|| format!("Failed to parse the cached POM of {}", coordinate));
+        }
+        if self.offline {
+            anyhow::bail!("POM of {} is not in the cache (offline mode)", coordinate);
+        }
+
+        let path = artifact_path(group_id, artifact_id, version, "pom");
+        let (repo, body) = self.fetch(group_id, Some(&coordinate), &path, 0).await?;
+        let text = body.text().await?;
+        let signature = match &self.signatures {
+            Some(verifier) => {
+                let asc = self.fetch_signature(repo, &path).await?;
+                Some(verifier.verify(group_id, text.as_bytes(), &asc, &shown_url(repo, &path))?)
+            }
+            None => None,
+        };
+        let project: Project = quick_xml::de::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", shown_url(repo, &path)))?;
+
+        cache::cache_pom(group_id, artifact_id, version, &text)?;
+        if let Some(signature) = &signature {
+            let file_name = format!("{}-{}.pom", artifact_id, version);
+            cache::cache_signature(group_id, artifact_id, version, &file_name, signature)?;
+        }
         Ok(project)
     }
 
-    pub fn download_jar(
+    /// The cached POM of an artifact, if it may be used. Released POMs never
+    /// change, but snapshots are fetched again unless offline. When signatures
+    /// are verified, only POMs whose signature was verified when cached count.
+    fn cached_pom(
         &self,
         group_id: &str,
         artifact_id: &str,
         version: &str,
-        output_path: &std::path::Path,
+    ) -> Result<Option<String>> {
+        if version.ends_with("-SNAPSHOT") && !self.offline {
+            return Ok(None);
+        }
+        let Some(text) = cache::get_cached_pom(group_id, artifact_id, version)? else {
+            return Ok(None);
+        };
+        if let Some(verifier) = &self.signatures {
+            let file_name = format!("{}-{}.pom", artifact_id, version);
+            match cache::get_cached_signer(group_id, artifact_id, version, &file_name)? {
+                Some(signer) => verifier.check_signer(group_id, &signer, &file_name)?,
+                None if self.offline => {
+                    return Err(SignatureError(format!(
+                        "{} was cached without its signature, which can't be fetched offline",
+                        file_name
+                    ))
+                    .into());
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(text))
+    }
+
+    /// Download a file to its `output_path`. It is written to a `.part` file first
+    /// and only renamed once it matches its locked checksum, if it has one, the
+    /// checksum file its repository publishes and, if signatures are verified,
+    /// its signature.
+    async fn download(
+        &self,
+        request: &DownloadRequest,
+        progress: &MultiProgress,
+    ) -> Result<Download> {
+        let path = repository_path(
+            &request.group_id,
+            &request.artifact_id,
+            &request.version,
+            &request.file_name,
+        );
+        let coordinate = format!(
+            "{}:{}:{}",
+            request.group_id, request.artifact_id, request.version
+        );
+        let part = cache::part_path(&request.output_path);
+
+        // What an interrupted install left in `part` is resumed. It may not be a
+        // prefix of this file after all, so if the result fails verification the
+        // file is fetched once more from the start.
+        let mut resumed = part.exists();
+        loop {
+            let offset = std::fs::metadata(&part).map_or(0, |m| m.len());
+            let (repo, body) = self
+                .fetch(&request.group_id, Some(&coordinate), &path, offset)
+                .await?;
+            match body {
+                Body::Http(response) => {
+                    let bar = progress.add(ProgressBar::new(0));
+                    bar.set_style(
+                        ProgressStyle::default_bar()
+                            .template(
+                                "  [{bar:25.cyan/blue}] {bytes:>10}/{total_bytes:10} {wide_msg}",
+                            )
+                            .unwrap()
+                            .progress_chars("#>-"),
+                    );
+                    bar.set_message(request.file_name.clone());
+                    let received = self.receive(repo, &path, response, &part, &bar).await;
+                    bar.finish_and_clear();
+                    received?;
+                }
+                Body::Local(bytes) => std::fs::write(&part, bytes)?,
+            }
+
+            let bytes = std::fs::read(&part)?;
+            let verified = match request.verify_locked(&bytes, &shown_url(repo, &path)) {
+                Ok(()) => self.verify(repo, &path, &request.group_id, &bytes).await,
+                Err(e) => Err(e),
+            };
+            match verified {
+                Ok((checksum, signature)) => {
+                    std::fs::rename(&part, &request.output_path)?;
+                    return Ok(Download {
+                        checksum,
+                        signature,
+                    });
+                }
+                Err(e) => {
+                    std::fs::remove_file(&part)?;
+                    if !resumed {
+                        return Err(e);
+                    }
+                    resumed = false;
+                }
+            }
+        }
+    }
+
+    /// Write the file at `path` in `repo` to `part`, starting with `response`,
+    /// which asked for the bytes after those already in `part`. A transfer that
+    /// breaks off is resumed too, up to `RetryPolicy::retries` times.
+    async fn receive(
+        &self,
+        repo: &Repository,
+        path: &str,
+        response: Response,
+        part: &Path,
+        bar: &ProgressBar,
     ) -> Result<()> {
-        let url = format!(
-            "{}/{}/{}/{}/{}-{}.jar",
-            self.base_url,
-            group_id.replace('.', "/"),
-            artifact_id,
-            version,
-            artifact_id,
-            version
+        let mut response = Some(response);
+        let mut retry = 0;
+        'transfer: loop {
+            let offset = std::fs::metadata(part).map_or(0, |m| m.len());
+            let mut response = match response.take() {
+                Some(response) => response,
+                None => self.get_from(repo, path, offset).await.map_err(|e| {
+                    anyhow::anyhow!("{} ({})", shown_url(repo, path), e.without_url())
+                })?,
+            };
+
+            let (mut file, offset) = match response.status() {
+                reqwest::StatusCode::PARTIAL_CONTENT => {
+                    (std::fs::OpenOptions::new().append(true).open(part)?, offset)
+                }
+                // The server ignored the range; start over
+                status if status.is_success() => (std::fs::File::create(part)?, 0),
+                // `part` is longer than the file; it can't be a prefix of it
+                reqwest::StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                    std::fs::remove_file(part)?;
+                    continue;
+                }
+                status => anyhow::bail!("Could not fetch {} ({})", shown_url(repo, path), status),
+            };
+            bar.set_length(offset + response.content_length().unwrap_or(0));
+            bar.set_position(offset);
+
+            loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        file.write_all(&chunk)?;
+                        bar.inc(chunk.len() as u64);
+                    }
+                    Ok(None) => return Ok(()),
+                    Err(_) if retry < self.retry.retries => {
+                        retry += 1;
+                        tokio::time::sleep(self.retry.delay(retry)).await;
+                        continue 'transfer;
+                    }
+                    Err(e) => anyhow::bail!(
+                        "Download of {} broke off: {}",
+                        shown_url(repo, path),
+                        e.without_url()
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Check `bytes` of the file at `path` against the checksum file `repo`
+    /// publishes for it and, if signatures are verified, its signature
+    async fn verify(
+        &self,
+        repo: &Repository,
+        path: &str,
+        group_id: &str,
+        bytes: &[u8],
+    ) -> Result<(Option<Checksum>, Option<Signature>)> {
+        let checksum = self.published_checksum(repo, path).await?;
+        if let Some(checksum) = &checksum {
+            checksum.verify(bytes, &shown_url(repo, path))?;
+        }
+        let signature = match &self.signatures {
+            Some(verifier) => {
+                let asc = self.fetch_signature(repo, path).await?;
+                Some(verifier.verify(group_id, bytes, &asc, &shown_url(repo, path))?)
+            }
+            None => None,
+        };
+        Ok((checksum, signature))
+    }
+
+    /// The strongest checksum `repo` publishes for the file at `path`
+    async fn published_checksum(&self, repo: &Repository, path: &str) -> Result<Option<Checksum>> {
+        if let Some(directory) = directory(repo) {
+            return directory.published_checksum(path);
+        }
+        for algorithm in checksum::Algorithm::ALL {
+            let path = format!("{}.{}", path, algorithm.extension());
+            let response = self
+                .get(repo, &path)
+                .await
+                .map_err(|e| anyhow::anyhow!("{} ({})", shown_url(repo, &path), e.without_url()))?;
+            match response.status() {
+                status if status.is_success() => {
+                    let checksum = Checksum::parse(algorithm, &response.text().await?)
+                        .map_err(|e| anyhow::anyhow!("{}: {}", shown_url(repo, &path), e))?;
+                    return Ok(Some(checksum));
+                }
+                // Buckets behind some repositories answer 403 for missing files
+                reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::FORBIDDEN => continue,
+                status => anyhow::bail!("Could not fetch {} ({})", shown_url(repo, &path), status),
+            }
+        }
+        Ok(None)
+    }
+
+    /// The `.asc` signature of the file at `path`, from `repo`, which served the file
+    async fn fetch_signature(&self, repo: &Repository, path: &str) -> Result<Vec<u8>> {
+        let asc = format!("{}.asc", path);
+        if let Some(directory) = directory(repo) {
+            return directory.read(&asc)?.ok_or_else(|| {
+                SignatureError(format!(
+                    "No signature for {} (not found)",
+                    shown_url(repo, path)
+                ))
+                .into()
+            });
+        }
+        let response = match self.get(repo, &asc).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                let status = response.status();
+                return Err(SignatureError(format!(
+                    "No signature for {} ({})",
+                    shown_url(repo, path),
+                    status
+                ))
+                .into());
+            }
+            Err(e) => {
+                return Err(SignatureError(format!(
+                    "No signature for {} ({})",
+                    shown_url(repo, path),
+                    e.without_url()
+                ))
+                .into());
+            }
+        };
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+impl RepositoryBackend for MavenClient {
+    /// The versions of an artifact. Online they are always fetched, as new ones
+    /// get published, and cached for `--offline`.
+    fn get_metadata(&self, group_id: &str, artifact_id: &str) -> Result<MavenMetadata> {
+        if self.offline {
+            let text = cache::get_cached_metadata(group_id, artifact_id)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Versions of {}:{} are not in the cache (offline mode)",
+                    group_id,
+                    artifact_id
+                )
+            })?;
+            return Ok(quick_xml::de::from_str(&text)?);
+        }
+
+        // Each repository may publish other versions; all of them are available
+        let repos = self.repositories_for(group_id, None);
+        let results: Vec<Result<MavenMetadata, String>> = self.runtime.block_on(
+            stream::iter(&repos)
+                .map(|repo| self.metadata_from(repo, group_id, artifact_id))
+                .buffered(MAX_CONCURRENT_REQUESTS)
+                .collect(),
         );
-        let mut response = self.client.get(&url).send()?;
-        let mut file = std::fs::File::create(output_path)?;
-        std::io::copy(&mut response, &mut file)?;
-        Ok(())
+        let mut merged: Option<MavenMetadata> = None;
+        let mut tried = Vec::new();
+        for result in results {
+            match (result, &mut merged) {
+                (Ok(metadata), Some(merged)) => merged.merge(metadata),
+                (Ok(metadata), None) => merged = Some(metadata),
+                (Err(reason), _) => tried.push(reason),
+            }
+        }
+        if let Some(metadata) = merged {
+            cache::cache_metadata(group_id, artifact_id, &metadata.to_xml()?)?;
+            return Ok(metadata);
+        }
+
+        if tried.is_empty() {
+            anyhow::bail!("No repository configured for group '{}'", group_id);
+        }
+        anyhow::bail!(
+            "Could not get the versions of {}:{} from any repository:\n  {}",
+            group_id,
+            artifact_id,
+            tried.join("\n  ")
+        )
+    }
+
+    fn get_pom(&self, group_id: &str, artifact_id: &str, version: &str) -> Result<Project> {
+        self.runtime
+            .block_on(self.pom(group_id, artifact_id, version))
+    }
+
+    /// POMs of several `(group, artifact, version)`s, fetched concurrently
+    fn get_poms(&self, coordinates: &[(String, String, String)]) -> Vec<Result<Project>> {
+        self.runtime.block_on(
+            stream::iter(coordinates)
+                .map(|(group_id, artifact_id, version)| self.pom(group_id, artifact_id, version))
+                .buffered(MAX_CONCURRENT_REQUESTS)
+                .collect(),
+        )
+    }
+
+    /// Download several files, a few at a time, each with a byte progress bar in
+    /// `progress`. Results are in the order of `requests`.
+    fn download_all(
+        &self,
+        requests: &[DownloadRequest],
+        progress: &MultiProgress,
+    ) -> Vec<Result<Download>> {
+        self.runtime.block_on(
+            stream::iter(requests)
+                .map(|request| self.download(request, progress))
+                .buffered(MAX_CONCURRENT_REQUESTS)
+                .collect(),
+        )
     }
 
-    pub fn search_artifact(&self, query: &str) -> Result<Vec<SearchResult>> {
+    fn search_artifact(&self, query: &str) -> Result<Vec<SearchResult>> {
         let url = "https://search.maven.org/solrsearch/select";
-        let response = self
-            .client
-            .get(url)
-            .header("User-Agent", "jpkg/0.1.0")
-            .query(&[("q", query), ("rows", "20"), ("wt", "json")])
-            .send()?;
+        self.runtime.block_on(async {
+            let response = self
+                .client
+                .get(url)
+                .header("User-Agent", "jpkg/0.1.0")
+                .query(&[("q", query), ("rows", "20"), ("wt", "json")])
+                .send()
+                .await?;
 
-        if !response.status().is_success() {
-            anyhow::bail!("Search failed: {}", response.status());
-        }
+            if !response.status().is_success() {
+                anyhow::bail!("Search failed: {}", response.status());
+            }
+
+            let text = response.text().await?;
+            // println!("Debug response: {}", text); // Uncomment for debugging
+            let response: SearchResponse = serde_json::from_str(&text)
+                .context(format!("Failed to parse search response: {}", text))?;
+
+            Ok(response.response.docs)
+        })
+    }
+
+    /// Name of the repository that served `coordinate` (or is preferred for it)
+    fn origin(&self, coordinate: &str) -> Option<String> {
+        self.origins.borrow().get(coordinate).cloned()
+    }
+
+    /// Where `file_name` of an artifact is downloaded from: its origin, or else the
+    /// first repository serving its group. Credentials in the URL are redacted.
+    fn artifact_url(
+        &self,
+        group_id: &str,
+        artifact_id: &str,
+        version: &str,
+        file_name: &str,
+    ) -> Option<String> {
+        let coordinate = format!("{}:{}:{}", group_id, artifact_id, version);
+        let repo = *self.repositories_for(group_id, Some(&coordinate)).first()?;
+        Some(shown_url(
+            repo,
+            &repository_path(group_id, artifact_id, version, file_name),
+        ))
+    }
+
+    fn signatures(&self) -> Option<&Verifier> {
+        self.signatures.as_ref()
+    }
+
+    /// The `.asc` signature of `file_name` of an artifact
+    fn signature(
+        &self,
+        group_id: &str,
+        artifact_id: &str,
+        version: &str,
+        file_name: &str,
+    ) -> Result<Vec<u8>> {
+        let path = repository_path(group_id, artifact_id, version, file_name);
+        let coordinate = format!("{}:{}:{}", group_id, artifact_id, version);
+        self.runtime.block_on(async {
+            let (_, body) = self
+                .fetch(group_id, Some(&coordinate), &format!("{}.asc", path), 0)
+                .await
+                .map_err(|e| SignatureError(format!("No signature for {}: {}", path, e)))?;
+            body.bytes().await
+        })
+    }
+}
 
-        let text = response.text()?;
-        // println!("Debug response: {}", text); // Uncomment for debugging
-        let response: SearchResponse = serde_json::from_str(&text)
-            .context(format!("Failed to parse search response: {}", text))?;
+/// Whether a response with `status` is worth another try: the server timed out
+/// or is overloaded, or a proxy in front of it failed
+fn is_transient(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
 
-        Ok(response.response.docs)
+/// The repository `repo` names, if it's a directory on the local filesystem
+fn directory(repo: &Repository) -> Option<DirectoryBackend> {
+    repo.local_path().map(DirectoryBackend::new)
+}
+
+/// The higher of two optional versions
+fn highest(a: Option<String>, b: Option<String>) -> Option<String> {
+    match (a, b) {
+        (Some(a), Some(b)) if version::compare(&b, &a).is_gt() => Some(b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// URL of `path` in `repo` to show in messages; they never show embedded passwords
+fn shown_url(repo: &Repository, path: &str) -> String {
+    let url = format!("{}/{}", repo.url.trim_end_matches('/'), path);
+    credentials::redact_url_userinfo(&url)
+}
+
+/// Repository-relative path of an artifact file, e.g. `com/google/guava/guava/33.0/guava-33.0.jar`
+pub fn artifact_path(group_id: &str, artifact_id: &str, version: &str, extension: &str) -> String {
+    let file_name = file_name(artifact_id, version, None, extension);
+    repository_path(group_id, artifact_id, version, &file_name)
+}
+
+pub fn repository_path(
+    group_id: &str,
+    artifact_id: &str,
+    version: &str,
+    file_name: &str,
+) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        group_id.replace('.', "/"),
+        artifact_id,
+        version,
+        file_name
+    )
+}
+
+/// File name of an artifact, e.g. `netty-transport-4.1.0-linux-x86_64.jar`
+pub fn file_name(
+    artifact_id: &str,
+    version: &str,
+    classifier: Option<&str>,
+    extension: &str,
+) -> String {
+    match classifier {
+        Some(classifier) => format!("{}-{}-{}.{}", artifact_id, version, classifier, extension),
+        None => format!("{}-{}.{}", artifact_id, version, extension),
+    }
+}
+
+/// Extension of the file to put on the classpath for a `<packaging>`, or `None`
+/// for `pom` packaging, which has nothing besides the POM
+pub fn packaging_extension(packaging: &str) -> Option<&'static str> {
+    match packaging {
+        "pom" => None,
+        // bundle, maven-plugin, ejb, ... are all jars
+        _ => Some("jar"),
     }
 }
 
@@ -113,82 +826,449 @@ pub struct SearchResult {
     pub latest_version: String,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MavenMetadata {
     pub group_id: String,
     pub artifact_id: String,
+    #[serde(default)]
     pub versioning: Versioning,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+impl MavenMetadata {
+    /// Add the versions another repository publishes; the highest latest and
+    /// release win
+    pub fn merge(&mut self, other: MavenMetadata) {
+        let ours = &mut self.versioning;
+        let theirs = other.versioning;
+        for version in theirs.versions.version {
+            if !ours.versions.version.contains(&version) {
+                ours.versions.version.push(version);
+            }
+        }
+        ours.versions.version.sort_by(|a, b| version::compare(a, b));
+        ours.latest = highest(ours.latest.take(), theirs.latest);
+        ours.release = highest(ours.release.take(), theirs.release);
+    }
+
+    /// As a `maven-metadata.xml`, e.g. to cache one made from a directory listing
+    pub fn to_xml(&self) -> Result<String> {
+        Ok(quick_xml::se::to_string_with_root("metadata", self)?)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Versioning {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub latest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub release: Option<String>,
+    #[serde(default)]
     pub versions: Versions,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Default)]
 pub struct Versions {
-    #[serde(rename = "version")]
+    #[serde(rename = "version", default)]
     pub version: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
 pub struct Project {
-    #[allow(dead_code)]
     pub group_id: Option<String>,
-    #[allow(dead_code)]
     pub artifact_id: String,
-    #[allow(dead_code)]
     pub version: Option<String>,
+    /// Not inherited from the parent; Maven's default is `jar`
+    pub packaging: Option<String>,
+    pub parent: Option<Parent>,
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    #[serde(default)]
+    pub dependency_management: DependencyManagement,
     #[serde(default)]
     pub dependencies: Dependencies,
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Parent {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DependencyManagement {
+    #[serde(default)]
+    pub dependencies: Dependencies,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Dependencies {
     #[serde(rename = "dependency", default)]
     pub dependency: Vec<Dependency>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Dependency {
     pub group_id: String,
     pub artifact_id: String,
     pub version: Option<String>,
     pub scope: Option<String>,
+    pub r#type: Option<String>,
+    pub classifier: Option<String>,
+    pub optional: Option<String>,
+    #[serde(default)]
+    pub exclusions: Exclusions,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Exclusions {
+    #[serde(rename = "exclusion", default)]
+    pub exclusion: Vec<Exclusion>,
+}
+
+/// A transitive artifact to leave out; either part may be the `*` wildcard
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub struct Exclusion {
+    pub group_id: String,
+    #[serde(default = "wildcard")]
+    pub artifact_id: String,
+}
+
+fn wildcard() -> String {
+    "*".to_string()
+}
+
+impl Dependency {
+    /// A `<scope>import</scope>` entry in dependencyManagement (Maven requires `<type>pom</type>`)
+    pub fn is_bom_import(&self) -> bool {
+        self.scope.as_deref() == Some("import") && self.r#type.as_deref() == Some("pom")
+    }
+
+    /// `<optional>true</optional>` dependencies are not pulled in transitively
+    pub fn is_optional(&self) -> bool {
+        self.optional.as_deref().map(str::trim) == Some("true")
+    }
+
+    /// `group:artifact` key used to match dependencies against managed entries
+    pub fn management_key(&self) -> String {
+        format!("{}:{}", self.group_id, self.artifact_id)
+    }
+}
+
+impl Exclusion {
+    /// Parse `group:artifact`, `group:*`, `*:artifact`, `group` or `*`
+    pub fn parse(pattern: &str) -> Result<Self> {
+        let (group, artifact) = pattern.split_once(':').unwrap_or((pattern, "*"));
+        if group.is_empty() || artifact.is_empty() || artifact.contains(':') {
+            anyhow::bail!(
+                "Invalid exclusion '{}' (expected group:artifact, * allowed)",
+                pattern
+            );
+        }
+        Ok(Exclusion {
+            group_id: group.to_string(),
+            artifact_id: artifact.to_string(),
+        })
+    }
+
+    /// Check whether this exclusion leaves out `artifact` (`group:artifact`)
+    pub fn matches(&self, artifact: &str) -> bool {
+        let (group, name) = artifact.split_once(':').unwrap_or((artifact, ""));
+        (self.group_id == "*" || self.group_id == group)
+            && (self.artifact_id == "*" || self.artifact_id == name)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn repo(name: &str, groups: &[&str]) -> Repository {
+        Repository {
+            name: name.to_string(),
+            url: format!("https://{}.example.com/maven2", name),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+
     #[test]
-    fn test_search_artifact_limit() {
-        let client = MavenClient::new();
-        // "spring" is a very common term
-        match client.search_artifact("spring") {
-            Ok(results) => {
-                assert!(
-                    results.len() <= 20,
-                    "Search results should be limited to 20"
-                );
-                assert!(
-                    !results.is_empty(),
-                    "Search should return some results for 'spring'"
-                );
-            }
-            Err(e) => {
-                // If the API is down (e.g. 502 Bad Gateway), we shouldn't fail the build
-                // but we should warn about it.
-                eprintln!(
-                    "Warning: Search test failed due to external API error: {}",
-                    e
+    fn test_central_is_appended_as_fallback() {
+        let client = MavenClient::with_repositories(&[repo("nexus", &[])]);
+        let names: Vec<&str> = client
+            .repositories_for("com.google.guava", None)
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["nexus", "central"]);
+    }
+
+    #[test]
+    fn test_repositories_filtered_by_group_and_preference() {
+        let client = MavenClient::with_repositories(&[
+            repo("nexus", &["com.mycorp.*"]),
+            repo("jitpack", &[]),
+        ]);
+
+        let names: Vec<&str> = client
+            .repositories_for("org.json", None)
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["jitpack", "central"]);
+
+        client.prefer("com.mycorp:core:1.0", "central");
+        let names: Vec<&str> = client
+            .repositories_for("com.mycorp", Some("com.mycorp:core:1.0"))
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["central", "nexus", "jitpack"]);
+    }
+
+    #[test]
+    fn test_parse_metadata() {
+        let metadata: MavenMetadata = quick_xml::de::from_str(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>org.json</groupId>
+  <artifactId>json</artifactId>
+  <versioning>
+    <latest>20240303</latest>
+    <release>20240303</release>
+    <versions>
+      <version>20231013</version>
+      <version>20240205</version>
+      <version>20240303</version>
+    </versions>
+    <lastUpdated>20240303175000</lastUpdated>
+  </versioning>
+</metadata>"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.versioning.versions.version.len(), 3);
+        assert_eq!(metadata.versioning.release.as_deref(), Some("20240303"));
+
+        let reparsed: MavenMetadata = quick_xml::de::from_str(&metadata.to_xml().unwrap()).unwrap();
+        assert_eq!(
+            reparsed.versioning.versions.version,
+            metadata.versioning.versions.version
+        );
+        assert_eq!(reparsed.versioning.release.as_deref(), Some("20240303"));
+    }
+
+    #[test]
+    fn test_merge_metadata() {
+        let metadata = |versions: &[&str], release: &str| MavenMetadata {
+            group_id: "g".to_string(),
+            artifact_id: "a".to_string(),
+            versioning: Versioning {
+                latest: Some(release.to_string()),
+                release: Some(release.to_string()),
+                versions: Versions {
+                    version: versions.iter().map(|v| v.to_string()).collect(),
+                },
+            },
+        };
+        let mut central = metadata(&["1.0", "1.10"], "1.10");
+        central.merge(metadata(&["1.0", "1.2", "1.9"], "1.9"));
+        assert_eq!(
+            central.versioning.versions.version,
+            vec!["1.0", "1.2", "1.9", "1.10"]
+        );
+        assert_eq!(central.versioning.latest.as_deref(), Some("1.10"));
+        assert_eq!(central.versioning.release.as_deref(), Some("1.10"));
+    }
+
+    /// `(path, range)` of every request a test server received
+    type RequestLog = std::sync::Arc<std::sync::Mutex<Vec<(String, Option<String>)>>>;
+
+    /// Serve `files` over HTTP, honouring `Range: bytes=N-`; returns the URL
+    fn serve(files: Vec<(&'static str, Vec<u8>)>) -> (String, RequestLog) {
+        use std::io::{BufRead, BufReader};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut lines = BufReader::new(&stream).lines().map(|l| l.unwrap());
+                let path = lines.next().unwrap().split(' ').nth(1).unwrap().to_string();
+                let range = lines.take_while(|line| !line.is_empty()).find_map(|line| {
+                    Some(
+                        line.to_lowercase()
+                            .strip_prefix("range: bytes=")?
+                            .to_string(),
+                    )
+                });
+                log.lock().unwrap().push((path.clone(), range.clone()));
+
+                let file = files.iter().find(|(p, _)| path == format!("/{}", p));
+                let (status, body) = match (file, &range) {
+                    (Some((_, body)), Some(range)) => {
+                        let from: usize = range.trim_end_matches('-').parse().unwrap();
+                        ("206 Partial Content", body[from..].to_vec())
+                    }
+                    (Some((_, body)), None) => ("200 OK", body.clone()),
+                    (None, _) => ("404 Not Found", Vec::new()),
+                };
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
                 );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
             }
+        });
+        (url, requests)
+    }
+
+    #[test]
+    fn test_download_resumes_and_restarts_a_bad_part() {
+        let jar = b"the real jar".to_vec();
+        let (url, requests) = serve(vec![
+            ("g/a/1.0/a-1.0.jar", jar.clone()),
+            (
+                "g/a/1.0/a-1.0.jar.sha1",
+                checksum::Algorithm::Sha1.digest(&jar).into_bytes(),
+            ),
+        ]);
+        let client = MavenClient::with_repositories(&[Repository {
+            name: "central".to_string(),
+            url,
+            groups: Vec::new(),
+        }]);
+
+        // A .part left by another file: resumed, rejected, then fetched whole
+        let temp = tempfile::tempdir().unwrap();
+        let output_path = temp.path().join("a-1.0.jar");
+        std::fs::write(cache::part_path(&output_path), "some").unwrap();
+        let request = DownloadRequest {
+            group_id: "g".to_string(),
+            artifact_id: "a".to_string(),
+            version: "1.0".to_string(),
+            file_name: "a-1.0.jar".to_string(),
+            output_path: output_path.clone(),
+            locked_checksum: None,
+        };
+        let results = client.download_all(&[request], &MultiProgress::new());
+        assert!(results[0].is_ok());
+        assert_eq!(std::fs::read(&output_path).unwrap(), jar);
+        assert!(!cache::part_path(&output_path).exists());
+
+        let jar_requests: Vec<Option<String>> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path.ends_with(".jar"))
+            .map(|(_, range)| range.clone())
+            .collect();
+        assert_eq!(jar_requests, vec![Some("4-".to_string()), None]);
+    }
+
+    #[test]
+    fn test_directory_repository_versions() {
+        let cache = tempfile::tempdir().unwrap();
+        cache::use_test_cache_dir(cache.path());
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/repo");
+        let client = MavenClient::with_repositories(&[Repository {
+            name: "central".to_string(),
+            url: fixtures.display().to_string(),
+            groups: Vec::new(),
+        }]);
+
+        // log has no maven-metadata.xml; its version directories are listed,
+        // and the result is cached like a fetched maven-metadata.xml
+        let log = client.get_metadata("com.example", "log").unwrap();
+        assert_eq!(log.versioning.versions.version, vec!["2.0"]);
+        let offline = client.with_offline(true);
+        let cached = offline.get_metadata("com.example", "log").unwrap();
+        assert_eq!(cached.versioning.versions.version, vec!["2.0"]);
+    }
+
+    #[test]
+    fn test_parse_exclusions() {
+        let dep: Dependency = quick_xml::de::from_str(
+            r#"<dependency>
+  <groupId>org.apache.hadoop</groupId>
+  <artifactId>hadoop-common</artifactId>
+  <version>3.3.6</version>
+  <exclusions>
+    <exclusion>
+      <groupId>log4j</groupId>
+      <artifactId>log4j</artifactId>
+    </exclusion>
+    <exclusion>
+      <groupId>org.slf4j</groupId>
+      <artifactId>*</artifactId>
+    </exclusion>
+  </exclusions>
+</dependency>"#,
+        )
+        .unwrap();
+        let exclusions = &dep.exclusions.exclusion;
+        assert_eq!(exclusions.len(), 2);
+        assert!(exclusions[0].matches("log4j:log4j"));
+        assert!(!exclusions[0].matches("log4j:log4j-core"));
+        assert!(exclusions[1].matches("org.slf4j:slf4j-api"));
+        assert!(!exclusions[1].matches("org.slf4j.ext:slf4j-ext"));
+    }
+
+    #[test]
+    fn test_exclusion_patterns() {
+        assert!(Exclusion::parse("*").unwrap().matches("any:thing"));
+        assert!(Exclusion::parse("*:*").unwrap().matches("any:thing"));
+        assert!(
+            Exclusion::parse("*:guava")
+                .unwrap()
+                .matches("com.google.guava:guava")
+        );
+        assert!(
+            Exclusion::parse("commons-logging")
+                .unwrap()
+                .matches("commons-logging:commons-logging")
+        );
+        assert!(Exclusion::parse("").is_err());
+        assert!(Exclusion::parse("a:b:c").is_err());
+    }
+
+    #[test]
+    fn test_artifact_path() {
+        assert_eq!(
+            artifact_path("com.google.guava", "guava", "33.0-jre", "pom"),
+            "com/google/guava/guava/33.0-jre/guava-33.0-jre.pom"
+        );
+        assert_eq!(
+            file_name("netty-transport", "4.1.0", Some("linux-x86_64"), "jar"),
+            "netty-transport-4.1.0-linux-x86_64.jar"
+        );
+        assert_eq!(packaging_extension("bundle"), Some("jar"));
+        assert_eq!(packaging_extension("pom"), None);
+    }
+
+    #[test]
+    fn test_transient_statuses() {
+        use reqwest::StatusCode;
+        for status in [
+            StatusCode::REQUEST_TIMEOUT,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert!(is_transient(status), "{}", status);
+        }
+        for status in [
+            StatusCode::OK,
+            StatusCode::NOT_FOUND,
+            StatusCode::UNAUTHORIZED,
+        ] {
+            assert!(!is_transient(status), "{}", status);
         }
     }
 }
@@ -1,10 +1,11 @@
+use crate::manifest::Scope;
 use anyhow::Result;
 use colored::Colorize;
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::Path;
-use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
 
 pub fn package_jar(output_name: Option<String>, main_class: Option<String>) -> Result<()> {
     let jar_name = output_name.unwrap_or_else(|| "app.jar".to_string());
@@ -29,9 +30,12 @@ pub fn package_jar(output_name: Option<String>, main_class: Option<String>) -> R
         );
     }
 
-    // Add dependencies from lib/
-    if Path::new("lib").exists() {
-        for entry in fs::read_dir("lib")? {
+    // Add compile and runtime dependencies; provided and test jars stay out
+    for lib_dir in [Scope::Compile.lib_dir(), Scope::Runtime.lib_dir()] {
+        if !Path::new(lib_dir).exists() {
+            continue;
+        }
+        for entry in fs::read_dir(lib_dir)? {
             let entry = entry?;
             let path = entry.path();
             if path.extension().and_then(|s| s.to_str()) == Some("jar") {
@@ -11,6 +11,22 @@ pub fn build_classpath(paths: &[&str]) -> String {
     paths.join(classpath_separator())
 }
 
+/// Jars for compiling src/main/java: compile and provided scopes
+pub const COMPILE_CLASSPATH: &[&str] = &["lib/*", "lib/provided/*"];
+
+/// Classes and jars for running the program: compile and runtime scopes
+pub const RUNTIME_CLASSPATH: &[&str] = &["bin", "lib/*", "lib/runtime/*"];
+
+/// Everything, for compiling and running tests
+pub const TEST_CLASSPATH: &[&str] = &[
+    "bin/test",
+    "bin",
+    "lib/*",
+    "lib/runtime/*",
+    "lib/provided/*",
+    "lib/test/*",
+];
+
 /// Convert a Unix-style path pattern to platform-specific
 /// e.g., "lib/*" becomes "lib\*" on Windows
 #[allow(dead_code)]
@@ -0,0 +1,541 @@
+use crate::backend::RepositoryBackend;
+use crate::maven::{Dependency, Project};
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+
+/// Maven refuses parent chains deeper than this; so do we (also guards against cycles)
+const MAX_PARENT_DEPTH: usize = 32;
+const MAX_INTERPOLATION_DEPTH: usize = 16;
+
+/// Builds effective POMs the way Maven does: walk the `<parent>` chain,
+/// inherit coordinates, properties, dependencyManagement and dependencies,
+/// import BOMs, then fill in missing versions and scopes from dependencyManagement.
+pub struct PomResolver<'a> {
+    client: &'a dyn RepositoryBackend,
+    /// Inherited POMs by `group:artifact:version`; parents are shared by many artifacts
+    cache: HashMap<String, Project>,
+    /// POMs fetched ahead of time by `prefetch`, not yet inherited
+    prefetched: HashMap<String, Project>,
+    /// Every `group:artifact:version` `prefetch` was asked for, whether it was found or not
+    requested: HashSet<String>,
+    /// Effective POMs by `group:artifact:version`; BOMs are imported by many artifacts
+    effective: HashMap<String, Project>,
+    /// BOMs currently being imported, to detect import cycles
+    importing: Vec<String>,
+}
+
+impl<'a> PomResolver<'a> {
+    pub fn new(client: &'a dyn RepositoryBackend) -> Self {
+        Self {
+            client,
+            cache: HashMap::new(),
+            prefetched: HashMap::new(),
+            requested: HashSet::new(),
+            effective: HashMap::new(),
+            importing: Vec::new(),
+        }
+    }
+
+    /// Whether the POM of `key` was asked for (by `prefetch` or otherwise) already
+    pub fn is_requested(&self, key: &str) -> bool {
+        self.requested.contains(key) || self.cache.contains_key(key)
+    }
+
+    /// Fetch the POMs of several `group:artifact:version`s and their parent
+    /// chains concurrently, ahead of `effective_pom`. POMs that can't be fetched
+    /// are left for `effective_pom` to report.
+    pub fn prefetch(&mut self, keys: &[String]) {
+        let mut wanted: Vec<String> = keys
+            .iter()
+            .filter(|key| !self.is_requested(key))
+            .cloned()
+            .collect();
+        wanted.sort();
+        wanted.dedup();
+
+        // Each round fetches the parents of the previous one
+        while !wanted.is_empty() {
+            let coordinates: Vec<(String, String, String)> = wanted
+                .iter()
+                .filter_map(|key| {
+                    let parts: Vec<&str> = key.split(':').collect();
+                    (parts.len() == 3).then(|| {
+                        (
+                            parts[0].to_string(),
+                            parts[1].to_string(),
+                            parts[2].to_string(),
+                        )
+                    })
+                })
+                .collect();
+            self.requested.extend(wanted.iter().cloned());
+            let poms = self.client.get_poms(&coordinates);
+
+            let mut parents = Vec::new();
+            for ((group, artifact, version), pom) in coordinates.into_iter().zip(poms) {
+                let Ok(pom) = pom else {
+                    continue;
+                };
+                if let Some(parent) = &pom.parent {
+                    let key = format!(
+                        "{}:{}:{}",
+                        parent.group_id, parent.artifact_id, parent.version
+                    );
+                    if !self.is_requested(&key) {
+                        parents.push(key);
+                    }
+                }
+                self.prefetched
+                    .insert(format!("{}:{}:{}", group, artifact, version), pom);
+            }
+            parents.sort();
+            parents.dedup();
+            wanted = parents;
+        }
+    }
+
+    pub fn effective_pom(&mut self, group: &str, artifact: &str, version: &str) -> Result<Project> {
+        let key = format!("{}:{}:{}", group, artifact, version);
+        if let Some(project) = self.effective.get(&key) {
+            return Ok(project.clone());
+        }
+        if self.importing.contains(&key) {
+            anyhow::bail!(
+                "BOM import cycle: {} -> {}",
+                self.importing.join(" -> "),
+                key
+            );
+        }
+
+        let mut project = self.inherited_pom(group, artifact, version, 0)?;
+        // Like Maven, interpolate after inheritance so `${project.version}` in a
+        // parent's dependencyManagement refers to the child's version
+        interpolate_project(&mut project);
+
+        self.importing.push(key.clone());
+        let imported = self.import_boms(&mut project);
+        self.importing.pop();
+        imported.with_context(|| format!("Failed to import BOMs of {}", key))?;
+
+        apply_management(&mut project);
+        self.effective.insert(key, project.clone());
+        Ok(project)
+    }
+
+    /// Managed dependencies of a BOM (`group:artifact:version`), by `group:artifact`
+    pub fn managed_versions(&mut self, bom: &str) -> Result<Vec<Dependency>> {
+        let parts: Vec<&str> = bom.split(':').collect();
+        if parts.len() != 3 {
+            anyhow::bail!("Invalid BOM '{}'. Expected group:artifact:version", bom);
+        }
+        let project = self.effective_pom(parts[0], parts[1], parts[2])?;
+        Ok(project.dependency_management.dependencies.dependency)
+    }
+
+    /// Replace `<scope>import</scope>` entries of dependencyManagement with the
+    /// managed dependencies of the imported BOMs. Entries declared directly win,
+    /// then earlier imports win over later ones.
+    fn import_boms(&mut self, project: &mut Project) -> Result<()> {
+        let entries = std::mem::take(&mut project.dependency_management.dependencies.dependency);
+        let (imports, mut managed): (Vec<_>, Vec<_>) =
+            entries.into_iter().partition(|d| d.is_bom_import());
+
+        for bom in imports {
+            let Some(version) = &bom.version else {
+                anyhow::bail!("Imported BOM {} has no version", bom.management_key());
+            };
+            let bom_pom = self.effective_pom(&bom.group_id, &bom.artifact_id, version)?;
+            merge_managed(
+                &mut managed,
+                &bom_pom.dependency_management.dependencies.dependency,
+            );
+        }
+
+        project.dependency_management.dependencies.dependency = managed;
+        Ok(())
+    }
+
+    /// The POM merged with its parent chain, not yet interpolated
+    fn inherited_pom(
+        &mut self,
+        group: &str,
+        artifact: &str,
+        version: &str,
+        depth: usize,
+    ) -> Result<Project> {
+        let key = format!("{}:{}:{}", group, artifact, version);
+        if let Some(project) = self.cache.get(&key) {
+            return Ok(project.clone());
+        }
+        if depth > MAX_PARENT_DEPTH {
+            anyhow::bail!("Parent POM chain of {} is too deep (cycle?)", key);
+        }
+
+        let mut project = match self.prefetched.remove(&key) {
+            Some(project) => project,
+            None => self.client.get_pom(group, artifact, version)?,
+        };
+
+        if let Some(parent) = project.parent.clone() {
+            let parent_pom = self
+                .inherited_pom(
+                    &parent.group_id,
+                    &parent.artifact_id,
+                    &parent.version,
+                    depth + 1,
+                )
+                .with_context(|| {
+                    format!(
+                        "Failed to resolve parent {}:{}:{} of {}",
+                        parent.group_id, parent.artifact_id, parent.version, key
+                    )
+                })?;
+            project = inherit(project, &parent_pom);
+        }
+
+        self.cache.insert(key, project.clone());
+        Ok(project)
+    }
+}
+
+/// Merge `parent` into `child`; anything the child declares itself wins
+pub fn inherit(mut child: Project, parent: &Project) -> Project {
+    if child.group_id.is_none() {
+        child.group_id = parent.group_id.clone();
+    }
+    if child.version.is_none() {
+        child.version = parent.version.clone();
+    }
+
+    for (name, value) in &parent.properties {
+        child
+            .properties
+            .entry(name.clone())
+            .or_insert_with(|| value.clone());
+    }
+
+    merge_managed(
+        &mut child.dependency_management.dependencies.dependency,
+        &parent.dependency_management.dependencies.dependency,
+    );
+
+    let declared: HashSet<String> = child
+        .dependencies
+        .dependency
+        .iter()
+        .map(|d| d.management_key())
+        .collect();
+    for dep in &parent.dependencies.dependency {
+        if !declared.contains(&dep.management_key()) {
+            child.dependencies.dependency.push(dep.clone());
+        }
+    }
+
+    child
+}
+
+/// Add the entries of `other` that `managed` does not manage yet
+pub fn merge_managed(managed: &mut Vec<Dependency>, other: &[Dependency]) {
+    let keys: HashSet<String> = managed.iter().map(|d| d.management_key()).collect();
+    for dep in other {
+        if !keys.contains(&dep.management_key()) {
+            managed.push(dep.clone());
+        }
+    }
+}
+
+/// Properties visible to `${...}` expressions: the POM's `<properties>`
+/// (including inherited ones) plus the built-in `project.*` values
+pub fn model_properties(project: &Project) -> HashMap<String, String> {
+    let mut properties = project.properties.clone();
+
+    let mut builtin = |name: &str, value: Option<&String>| {
+        if let Some(value) = value {
+            for prefix in ["project.", "pom."] {
+                properties.insert(format!("{}{}", prefix, name), value.clone());
+            }
+        }
+    };
+    builtin("groupId", project.group_id.as_ref());
+    builtin("artifactId", Some(&project.artifact_id));
+    builtin("version", project.version.as_ref());
+    if let Some(parent) = &project.parent {
+        builtin("parent.groupId", Some(&parent.group_id));
+        builtin("parent.artifactId", Some(&parent.artifact_id));
+        builtin("parent.version", Some(&parent.version));
+    }
+
+    properties
+}
+
+/// Replace `${name}` references in `value`. Property values may refer to
+/// other properties; unknown references are left untouched.
+pub fn interpolate(value: &str, properties: &HashMap<String, String>) -> String {
+    let mut result = value.to_string();
+
+    // Bounded so self-referencing properties can't loop forever
+    for _ in 0..MAX_INTERPOLATION_DEPTH {
+        let mut changed = false;
+        let mut output = String::with_capacity(result.len());
+        let mut rest = result.as_str();
+
+        while let Some(start) = rest.find("${") {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 2..start + len];
+            output.push_str(&rest[..start]);
+            match properties.get(name) {
+                Some(replacement) => {
+                    output.push_str(replacement);
+                    changed = true;
+                }
+                None => output.push_str(&rest[start..=start + len]),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        output.push_str(rest);
+        result = output;
+
+        if !changed {
+            break;
+        }
+    }
+
+    result
+}
+
+/// Interpolate the coordinates and scopes of all dependencies and managed dependencies
+fn interpolate_project(project: &mut Project) {
+    let properties = model_properties(project);
+    let deps = project.dependencies.dependency.iter_mut().chain(
+        project
+            .dependency_management
+            .dependencies
+            .dependency
+            .iter_mut(),
+    );
+
+    for dep in deps {
+        dep.group_id = interpolate(&dep.group_id, &properties);
+        dep.artifact_id = interpolate(&dep.artifact_id, &properties);
+        for field in [&mut dep.version, &mut dep.scope].into_iter().flatten() {
+            *field = interpolate(field, &properties);
+        }
+        for exclusion in &mut dep.exclusions.exclusion {
+            exclusion.group_id = interpolate(&exclusion.group_id, &properties);
+            exclusion.artifact_id = interpolate(&exclusion.artifact_id, &properties);
+        }
+    }
+}
+
+/// Fill in versions, scopes and exclusions of dependencies from dependencyManagement
+pub fn apply_management(project: &mut Project) {
+    let managed: HashMap<String, _> = project
+        .dependency_management
+        .dependencies
+        .dependency
+        .iter()
+        .map(|d| (d.management_key(), d.clone()))
+        .collect();
+
+    for dep in &mut project.dependencies.dependency {
+        if let Some(managed) = managed.get(&dep.management_key()) {
+            if dep.version.is_none() {
+                dep.version = managed.version.clone();
+            }
+            if dep.scope.is_none() {
+                dep.scope = managed.scope.clone();
+            }
+            if dep.exclusions.exclusion.is_empty() {
+                dep.exclusions = managed.exclusions.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <modelVersion>4.0.0</modelVersion>
+  <groupId>com.example</groupId>
+  <artifactId>parent</artifactId>
+  <version>2.1.0</version>
+  <packaging>pom</packaging>
+  <properties>
+    <slf4j.version>2.0.9</slf4j.version>
+    <java.version>17</java.version>
+  </properties>
+  <dependencyManagement>
+    <dependencies>
+      <dependency>
+        <groupId>org.slf4j</groupId>
+        <artifactId>slf4j-api</artifactId>
+        <version>${slf4j.version}</version>
+      </dependency>
+      <dependency>
+        <groupId>com.example</groupId>
+        <artifactId>shared</artifactId>
+        <version>${project.version}</version>
+      </dependency>
+      <dependency>
+        <groupId>junit</groupId>
+        <artifactId>junit</artifactId>
+        <version>4.13.2</version>
+        <scope>test</scope>
+      </dependency>
+    </dependencies>
+  </dependencyManagement>
+  <dependencies>
+    <dependency>
+      <groupId>com.google.code.findbugs</groupId>
+      <artifactId>jsr305</artifactId>
+      <version>3.0.2</version>
+    </dependency>
+  </dependencies>
+</project>"#;
+
+    const CHILD: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0">
+  <modelVersion>4.0.0</modelVersion>
+  <parent>
+    <groupId>com.example</groupId>
+    <artifactId>parent</artifactId>
+    <version>2.1.0</version>
+  </parent>
+  <artifactId>core</artifactId>
+  <properties>
+    <java.version>21</java.version>
+  </properties>
+  <dependencies>
+    <dependency>
+      <groupId>org.slf4j</groupId>
+      <artifactId>slf4j-api</artifactId>
+    </dependency>
+    <dependency>
+      <groupId>junit</groupId>
+      <artifactId>junit</artifactId>
+    </dependency>
+    <dependency>
+      <groupId>${project.groupId}</groupId>
+      <artifactId>shared</artifactId>
+    </dependency>
+  </dependencies>
+</project>"#;
+
+    #[test]
+    fn test_parse_parent_and_management() {
+        let parent: Project = quick_xml::de::from_str(PARENT).unwrap();
+        assert_eq!(parent.group_id.as_deref(), Some("com.example"));
+        assert_eq!(parent.properties["slf4j.version"], "2.0.9");
+        assert_eq!(
+            parent.dependency_management.dependencies.dependency.len(),
+            3
+        );
+
+        let child: Project = quick_xml::de::from_str(CHILD).unwrap();
+        let declared = child.parent.as_ref().unwrap();
+        assert_eq!(declared.artifact_id, "parent");
+        assert_eq!(declared.version, "2.1.0");
+        assert!(child.group_id.is_none());
+    }
+
+    #[test]
+    fn test_inherit_and_apply_management() {
+        let parent: Project = quick_xml::de::from_str(PARENT).unwrap();
+        let child: Project = quick_xml::de::from_str(CHILD).unwrap();
+
+        let mut effective = inherit(child, &parent);
+        interpolate_project(&mut effective);
+        apply_management(&mut effective);
+
+        assert_eq!(effective.group_id.as_deref(), Some("com.example"));
+        assert_eq!(effective.version.as_deref(), Some("2.1.0"));
+        assert_eq!(effective.properties["java.version"], "21");
+        assert_eq!(effective.properties["slf4j.version"], "2.0.9");
+
+        let deps = &effective.dependencies.dependency;
+        assert_eq!(deps.len(), 4);
+        assert_eq!(deps[0].version.as_deref(), Some("2.0.9"));
+        assert_eq!(deps[1].version.as_deref(), Some("4.13.2"));
+        assert_eq!(deps[1].scope.as_deref(), Some("test"));
+        assert_eq!(deps[2].group_id, "com.example");
+        assert_eq!(deps[2].version.as_deref(), Some("2.1.0"));
+        assert_eq!(deps[3].artifact_id, "jsr305");
+    }
+
+    #[test]
+    fn test_parse_bom_import() {
+        let pom: Project = quick_xml::de::from_str(
+            r#"<project>
+  <artifactId>app</artifactId>
+  <dependencyManagement>
+    <dependencies>
+      <dependency>
+        <groupId>com.fasterxml.jackson</groupId>
+        <artifactId>jackson-bom</artifactId>
+        <version>2.17.0</version>
+        <type>pom</type>
+        <scope>import</scope>
+      </dependency>
+    </dependencies>
+  </dependencyManagement>
+</project>"#,
+        )
+        .unwrap();
+        let bom = &pom.dependency_management.dependencies.dependency[0];
+        assert!(bom.is_bom_import());
+    }
+
+    #[test]
+    fn test_merge_managed_keeps_first_entry() {
+        let parent: Project = quick_xml::de::from_str(PARENT).unwrap();
+        let mut managed = parent.dependency_management.dependencies.dependency.clone();
+        managed[0].version = Some("1.7.36".to_string());
+        let before = managed.len();
+
+        merge_managed(
+            &mut managed,
+            &parent.dependency_management.dependencies.dependency,
+        );
+        assert_eq!(managed.len(), before);
+        assert_eq!(managed[0].version.as_deref(), Some("1.7.36"));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let properties: HashMap<String, String> = [
+            ("jackson.version", "2.17.0"),
+            ("jackson.bom", "${jackson.version}"),
+            ("loop", "${loop}"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        assert_eq!(interpolate("${jackson.version}", &properties), "2.17.0");
+        assert_eq!(interpolate("${jackson.bom}", &properties), "2.17.0");
+        assert_eq!(
+            interpolate("v${jackson.version}-jre", &properties),
+            "v2.17.0-jre"
+        );
+        assert_eq!(interpolate("${missing}", &properties), "${missing}");
+        assert_eq!(interpolate("${loop}", &properties), "${loop}");
+        assert_eq!(interpolate("${unclosed", &properties), "${unclosed");
+    }
+
+    #[test]
+    fn test_builtin_properties() {
+        let child: Project = quick_xml::de::from_str(CHILD).unwrap();
+        let parent: Project = quick_xml::de::from_str(PARENT).unwrap();
+        let properties = model_properties(&inherit(child, &parent));
+
+        assert_eq!(properties["project.artifactId"], "core");
+        assert_eq!(properties["project.version"], "2.1.0");
+        assert_eq!(properties["project.parent.version"], "2.1.0");
+        assert_eq!(properties["pom.groupId"], "com.example");
+    }
+}
@@ -94,7 +94,7 @@ pub fn build_project(verbose: bool) -> Result<()> {
 
     let mut cmd = Command::new("javac");
     cmd.arg("-d").arg("bin");
-    let classpath = platform::build_classpath(&["lib/*"]);
+    let classpath = platform::build_classpath(platform::COMPILE_CLASSPATH);
     cmd.arg("-cp").arg(&classpath);
     cmd.arg("-sourcepath").arg("src/main/java");
 
@@ -152,13 +152,13 @@ pub fn build_project(verbose: bool) -> Result<()> {
 pub fn run_project(main_class: Option<String>, verbose: bool) -> Result<()> {
     let main = main_class.unwrap_or_else(|| "Main".to_string());
 
+    let classpath = platform::build_classpath(platform::RUNTIME_CLASSPATH);
     if verbose {
         println!("{}", format!("🚀 Running {}...", main).cyan());
-        println!("{}", format!("   Classpath: bin:lib/*").dimmed());
+        println!("{}", format!("   Classpath: {}", classpath).dimmed());
     }
 
     let mut cmd = Command::new("java");
-    let classpath = platform::build_classpath(&["bin", "lib/*"]);
     cmd.arg("-cp").arg(&classpath).arg(&main);
 
     let output = cmd.output().context("Failed to run java")?;
@@ -204,10 +204,8 @@ fn visit_dirs(dir: &Path, cb: &mut Vec<std::path::PathBuf>) -> Result<()> {
             let path = entry.path();
             if path.is_dir() {
                 visit_dirs(&path, cb)?;
-            } else if let Some(ext) = path.extension() {
-                if ext == "java" {
-                    cb.push(path);
-                }
+            } else if path.extension().is_some_and(|ext| ext == "java") {
+                cb.push(path);
             }
         }
     }
@@ -1,113 +1,1361 @@
-use crate::manifest::Manifest;
-use crate::maven::MavenClient;
-use anyhow::Result;
-use std::collections::HashMap;
-use varisat::{ExtendFormula, Lit, Solver};
+use crate::backend::RepositoryBackend;
+use crate::graph::{DependencyGraph, GraphEdge};
+use crate::manifest::{ConflictStrategy, Manifest, Scope};
+use crate::maven::Exclusion;
+use crate::pom::PomResolver;
+use crate::version::{self, VersionRange, is_range};
+use anyhow::{Context, Result};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use varisat::{ExtendFormula, Lit, Solver, Var};
+
+/// `parent` (group:artifact:version) needs some version of `artifact` (group:artifact)
+struct Edge {
+    parent: String,
+    artifact: String,
+    /// The version or range as written in the POM
+    spec: String,
+    /// Versions satisfying a version range; `None` means any version will do
+    allowed: Option<Vec<String>>,
+    /// Scope declared in the parent's POM
+    scope: Scope,
+    classifier: Option<String>,
+}
+
+/// A dependency declared in a POM
+struct Requirement {
+    /// `group:artifact`
+    artifact: String,
+    /// Version or range
+    spec: String,
+    scope: Scope,
+    classifier: Option<String>,
+    /// Left out of this dependency's subtree
+    exclusions: Vec<Exclusion>,
+}
+
+/// Where a guarded clause came from, so a failed core can be explained
+enum Reason {
+    /// A dependency in jpkg.json
+    Root { artifact: String, spec: String },
+    /// An entry of `Resolver::edges`
+    Edge(usize),
+    /// An entry of `Resolver::unreadable`: that version can't be selected
+    Unreadable(usize),
+}
+
+/// An artifact for which more than one version was requested
+pub struct Conflict {
+    pub artifact: String,
+    pub selected: String,
+    pub rejected: Vec<String>,
+}
 
 pub struct Resolver<'a> {
-    client: &'a MavenClient,
+    client: &'a dyn RepositoryBackend,
+    poms: PomResolver<'a>,
     manifest: &'a Manifest,
-    var_map: HashMap<String, varisat::Var>,
-    rev_map: HashMap<varisat::Var, String>,
+    strategy: ConflictStrategy,
+    var_map: HashMap<String, Var>,
+    rev_map: HashMap<Var, String>,
+    /// Versions managed by the manifest's BOMs, by `group:artifact`
+    managed: HashMap<String, String>,
+    /// Versions of each `group:artifact` that somebody asked for, in the order
+    /// they were first asked for
+    candidates: HashMap<String, Vec<String>>,
+    /// Published versions of each `group:artifact`, from maven-metadata.xml
+    available: HashMap<String, Vec<String>>,
+    /// Every `group:artifact` seen, in breadth-first order
+    artifacts: Vec<String>,
+    edges: Vec<Edge>,
+    /// Direct dependencies (`group:artifact`, version or range) and the section they're declared in
+    roots: Vec<(String, String, Scope)>,
+    /// Candidate `group:artifact:version`s of each of `roots`
+    root_keys: Vec<Vec<String>>,
+    /// Selected version of each `group:artifact`
+    selected: HashMap<String, String>,
+    /// Scope of each selected `group:artifact:version`
+    scopes: HashMap<String, Scope>,
+    /// `<packaging>` of each `group:artifact:version` whose POM was read
+    packaging: HashMap<String, String>,
+    /// `(parent, artifact, spec)` of every edge, so revisiting a node adds no duplicates
+    edge_set: HashSet<(String, String, String)>,
+    /// `group:artifact:version`s whose POM couldn't be read, and why
+    unreadable: Vec<(String, String)>,
+    /// Requirement clauses are guarded by a selector variable: `!Selector v Clause`.
+    /// Solving assumes all selectors, and the failed core names the clauses that clash.
+    selectors: Vec<(Var, Reason)>,
+    /// Variables are numbered here rather than by the solver, which is rebuilt
+    /// for every `select` as the graph grows
+    next_var: usize,
+    /// The node each `group:artifact:version` was first reached from (shortest path)
+    reached_from: HashMap<String, String>,
+    conflicts: Vec<Conflict>,
     solver: Solver<'a>,
 }
 
 impl<'a> Resolver<'a> {
-    pub fn new(client: &'a MavenClient, manifest: &'a Manifest) -> Self {
+    pub fn new(client: &'a dyn RepositoryBackend, manifest: &'a Manifest) -> Self {
         Self {
             client,
+            poms: PomResolver::new(client),
             manifest,
+            strategy: manifest.conflict_strategy,
             var_map: HashMap::new(),
             rev_map: HashMap::new(),
+            managed: HashMap::new(),
+            candidates: HashMap::new(),
+            available: HashMap::new(),
+            artifacts: Vec::new(),
+            edges: Vec::new(),
+            roots: Vec::new(),
+            root_keys: Vec::new(),
+            selected: HashMap::new(),
+            scopes: HashMap::new(),
+            packaging: HashMap::new(),
+            edge_set: HashSet::new(),
+            unreadable: Vec::new(),
+            selectors: Vec::new(),
+            next_var: 0,
+            reached_from: HashMap::new(),
+            conflicts: Vec::new(),
             solver: Solver::new(),
         }
     }
 
+    /// Override the manifest's conflict strategy
+    pub fn with_strategy(mut self, strategy: ConflictStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn strategy(&self) -> ConflictStrategy {
+        self.strategy
+    }
+
+    /// Artifacts that were requested in several versions, after `resolve`
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Scope of a selected `group:artifact:version`
+    pub fn scope(&self, key: &str) -> Scope {
+        self.scopes.get(key).copied().unwrap_or_default()
+    }
+
+    /// `<packaging>` of a `group:artifact:version` (`jar` unless its POM says otherwise)
+    pub fn packaging(&self, key: &str) -> &str {
+        self.packaging.get(key).map_or("jar", String::as_str)
+    }
+
+    /// Classifier of a selected `group:artifact:version`, if every request for it names the same one
+    pub fn classifier(&self, key: &str) -> Option<&str> {
+        let (artifact, _) = key.rsplit_once(':')?;
+        if self.roots.iter().any(|(root, _, _)| root == artifact) {
+            return None;
+        }
+        let mut classifiers = self
+            .edges
+            .iter()
+            .filter(|edge| edge.artifact == artifact)
+            .map(|edge| edge.classifier.as_deref());
+        let first = classifiers.next()??;
+        classifiers.all(|c| c == Some(first)).then_some(first)
+    }
+
+    /// The selected artifacts and the edges between them, after `resolve`.
+    /// jpkg.json's own requests are edges from the project node.
+    pub fn graph(&self) -> DependencyGraph {
+        let package = format!(
+            "{} v{}",
+            self.manifest.package.name, self.manifest.package.version
+        );
+
+        let mut roots: Vec<&(String, String, Scope)> = self.roots.iter().collect();
+        roots.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        let requests = roots
+            .into_iter()
+            .map(|(artifact, spec, _)| (&package, artifact, spec, is_range(spec)))
+            .chain(
+                self.edges
+                    .iter()
+                    .filter(|edge| {
+                        let (artifact, version) =
+                            edge.parent.rsplit_once(':').unwrap_or((&edge.parent, ""));
+                        self.selected.get(artifact).map(String::as_str) == Some(version)
+                    })
+                    .map(|edge| {
+                        (
+                            &edge.parent,
+                            &edge.artifact,
+                            &edge.spec,
+                            edge.allowed.is_some(),
+                        )
+                    }),
+            );
+
+        let mut edges: Vec<GraphEdge> = Vec::new();
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+        for (parent, artifact, spec, range) in requests {
+            let Some(selected) = self.selected.get(artifact) else {
+                continue;
+            };
+            let child = format!("{}:{}", artifact, selected);
+            // A plain version that wasn't picked lost a conflict; ranges always hold
+            let conflict = !range && version::compare(spec, selected) != std::cmp::Ordering::Equal;
+
+            // The same parent may ask for the child more than once (e.g. via a range and a version)
+            match index.get(&(parent.clone(), child.clone())) {
+                Some(&i) => edges[i].conflict &= conflict,
+                None => {
+                    index.insert((parent.clone(), child.clone()), edges.len());
+                    edges.push(GraphEdge {
+                        parent: parent.clone(),
+                        child,
+                        requested: spec.clone(),
+                        conflict,
+                    });
+                }
+            }
+        }
+
+        DependencyGraph {
+            package,
+            scopes: self.scopes.clone(),
+            edges,
+        }
+    }
+
     pub fn resolve(&mut self) -> Result<Vec<String>> {
-        for (name, version) in &self.manifest.dependencies {
-            let key = format!("{}:{}", name, version);
-            let var = self.get_or_create_var(&key);
-            // Root deps must be true
-            self.solver.add_clause(&[Lit::from_var(var, true)]);
+        for bom in &self.manifest.boms {
+            let entries = self
+                .poms
+                .managed_versions(bom)
+                .with_context(|| format!("Failed to load BOM {}", bom))?;
+            for dep in entries {
+                if let Some(version) = &dep.version {
+                    // The first BOM that manages an artifact wins
+                    self.managed
+                        .entry(dep.management_key())
+                        .or_insert_with(|| version.clone());
+                }
+            }
+        }
+
+        // Sorted, so ties between direct dependencies break the same way every time
+        let mut roots: Vec<_> = self.manifest.all_dependencies().collect();
+        roots.sort_by(|a, b| a.0.cmp(b.0));
 
-            self.resolve_deps(&key, var)?;
+        let mut pending = Vec::new();
+        for (name, spec, scope) in roots {
+            let version = if spec.version().is_empty() {
+                self.managed.get(name).cloned().with_context(|| {
+                    format!("No version given for {} and no BOM manages it", name)
+                })?
+            } else {
+                spec.version().to_string()
+            };
+            let exclusions = spec
+                .exclusions()
+                .iter()
+                .map(|pattern| Exclusion::parse(pattern))
+                .collect::<Result<BTreeSet<_>>>()
+                .with_context(|| format!("Invalid exclusions for {}", name))?;
+            for key in self.add_root(name, &version, scope)? {
+                pending.push((key, exclusions.clone()));
+            }
         }
 
-        let solution = self.solver.solve().unwrap();
+        // Only versions the solver selects are expanded, so a range doesn't read
+        // the POM of every version it matches. Expanding them may change the
+        // selection, so repeat until every selected version is expanded.
+        let mut expanded: HashMap<String, BTreeSet<Exclusion>> = HashMap::new();
+        loop {
+            let resolved: HashSet<String> = self.select()?.into_iter().collect();
+            let (ready, waiting): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(key, _)| resolved.contains(key));
+            if ready.is_empty() {
+                let mut resolved: Vec<String> = resolved.into_iter().collect();
+                resolved.sort();
+                return Ok(resolved);
+            }
+            pending = waiting;
+            pending.extend(self.expand(ready, &mut expanded)?);
+        }
+    }
 
-        if solution {
-            let model = self.solver.model().unwrap();
-            let mut resolved = Vec::new();
-            for lit in model {
-                if lit.is_positive() {
-                    let var = lit.var();
-                    if let Some(key) = self.rev_map.get(&var) {
-                        resolved.push(key.clone());
-                    }
+    /// Add the dependencies of `ready` nodes to the graph, and return the
+    /// nodes they lead to with the exclusions that apply there
+    fn expand(
+        &mut self,
+        ready: Vec<(String, BTreeSet<Exclusion>)>,
+        expanded: &mut HashMap<String, BTreeSet<Exclusion>>,
+    ) -> Result<Vec<(String, BTreeSet<Exclusion>)>> {
+        // Fetch all their POMs at once, rather than one by one
+        let keys: Vec<String> = ready
+            .iter()
+            .map(|(key, _)| key.clone())
+            .filter(|key| !self.without_pom(key))
+            .collect();
+        self.poms.prefetch(&keys);
+
+        // Exclusions apply to a whole subtree; a node reached along several paths
+        // only excludes what every path excludes.
+        let mut children = Vec::new();
+        for (key, exclusions) in ready {
+            let exclusions = match expanded.get(&key) {
+                Some(previous) if previous.is_subset(&exclusions) => continue,
+                Some(previous) => previous.intersection(&exclusions).cloned().collect(),
+                None => exclusions,
+            };
+            expanded.insert(key.clone(), exclusions.clone());
+
+            let requirements = match self.dependencies_of(&key) {
+                Ok(requirements) => requirements,
+                // Only fatal if no other version will do
+                Err(e) => {
+                    self.unreadable.push((key, format!("{:#}", e)));
+                    continue;
+                }
+            };
+            for requirement in requirements {
+                if exclusions.iter().any(|e| e.matches(&requirement.artifact)) {
+                    continue;
+                }
+                let mut child_exclusions = exclusions.clone();
+                child_exclusions.extend(requirement.exclusions);
+                let keys = self.add_edge(
+                    &key,
+                    &requirement.artifact,
+                    &requirement.spec,
+                    requirement.scope,
+                    requirement.classifier,
+                )?;
+                for child in keys {
+                    children.push((child, child_exclusions.clone()));
                 }
             }
-            Ok(resolved)
-        } else {
-            anyhow::bail!("Unsatisfiable dependencies")
         }
+        Ok(children)
     }
 
-    fn get_or_create_var(&mut self, key: &str) -> varisat::Var {
+    fn new_var(&mut self) -> Var {
+        self.next_var += 1;
+        Var::from_index(self.next_var - 1)
+    }
+
+    fn get_or_create_var(&mut self, key: &str) -> Var {
         if let Some(&var) = self.var_map.get(key) {
             var
         } else {
-            let var = self.solver.new_var();
+            let var = self.new_var();
             self.var_map.insert(key.to_string(), var);
             self.rev_map.insert(var, key.to_string());
             var
         }
     }
 
-    fn resolve_deps(&mut self, parent_key: &str, parent_var: varisat::Var) -> Result<()> {
-        let parts: Vec<&str> = parent_key.split(':').collect();
-        if parts.len() != 3 {
-            return Ok(());
+    /// A dependency from jpkg.json; its version (or one in its range) must be selected
+    fn add_root(&mut self, artifact: &str, spec: &str, scope: Scope) -> Result<Vec<String>> {
+        let keys = match self.expand_range(artifact, spec)? {
+            Some(versions) => {
+                if versions.is_empty() {
+                    anyhow::bail!("No published version of {} matches {}", artifact, spec);
+                }
+                versions
+                    .iter()
+                    .map(|v| self.add_candidate(artifact, v))
+                    .collect()
+            }
+            None => vec![self.add_candidate(artifact, spec)],
+        };
+
+        self.roots
+            .push((artifact.to_string(), spec.to_string(), scope));
+        self.root_keys.push(keys.clone());
+        Ok(keys)
+    }
+
+    /// `parent` asks for `artifact` at `spec` (a version or a version range)
+    fn add_edge(
+        &mut self,
+        parent: &str,
+        artifact: &str,
+        spec: &str,
+        scope: Scope,
+        classifier: Option<String>,
+    ) -> Result<Vec<String>> {
+        let allowed = self.expand_range(artifact, spec)?;
+        let keys = match &allowed {
+            Some(versions) => versions
+                .iter()
+                .map(|v| self.add_candidate(artifact, v))
+                .collect(),
+            None => vec![self.add_candidate(artifact, spec)],
+        };
+
+        for key in &keys {
+            if !self.reached_from.contains_key(key) && key != parent {
+                self.reached_from.insert(key.clone(), parent.to_string());
+            }
+        }
+
+        let edge = (parent.to_string(), artifact.to_string(), spec.to_string());
+        if !self.edge_set.insert(edge) {
+            return Ok(keys);
+        }
+        self.edges.push(Edge {
+            parent: parent.to_string(),
+            artifact: artifact.to_string(),
+            spec: spec.to_string(),
+            allowed,
+            scope,
+            classifier,
+        });
+        Ok(keys)
+    }
+
+    /// Published versions matching `spec`, highest first, or `None` if `spec` is not a range
+    fn expand_range(&mut self, artifact: &str, spec: &str) -> Result<Option<Vec<String>>> {
+        let Some(range) = VersionRange::parse(spec)? else {
+            return Ok(None);
+        };
+
+        if !self.available.contains_key(artifact) {
+            let (group, name) = artifact.split_once(':').unwrap_or((artifact, ""));
+            let metadata = self.client.get_metadata(group, name).with_context(|| {
+                format!("Failed to list versions of {} for range {}", artifact, spec)
+            })?;
+            self.available
+                .insert(artifact.to_string(), metadata.versioning.versions.version);
+        }
+
+        let matching = range
+            .matching(&self.available[artifact])
+            .into_iter()
+            .cloned()
+            .collect();
+        Ok(Some(matching))
+    }
+
+    fn add_candidate(&mut self, artifact: &str, version: &str) -> String {
+        if !self.candidates.contains_key(artifact) {
+            self.artifacts.push(artifact.to_string());
+        }
+        let candidates = self.candidates.entry(artifact.to_string()).or_default();
+        if !candidates.iter().any(|v| v == version) {
+            candidates.push(version.to_string());
+        }
+
+        let key = format!("{}:{}", artifact, version);
+        self.get_or_create_var(&key);
+        key
+    }
+
+    /// Whether jpkg.json says `key` (`group:artifact:version`) has no POM
+    fn without_pom(&self, key: &str) -> bool {
+        let artifact = key.rsplit_once(':').map_or(key, |(artifact, _)| artifact);
+        self.manifest
+            .all_dependencies()
+            .any(|(name, spec, _)| name == artifact && !spec.has_pom())
+    }
+
+    /// Dependencies declared by the POM of `key`. A POM that can't be fetched or
+    /// parsed is an error, unless jpkg.json says the artifact has none, and so is
+    /// a dependency without a version or with an undefined property.
+    fn dependencies_of(&mut self, key: &str) -> Result<Vec<Requirement>> {
+        let parts: Vec<&str> = key.split(':').collect();
+        if parts.len() != 3 || self.without_pom(key) {
+            return Ok(Vec::new());
         }
         let group = parts[0];
         let artifact = parts[1];
         let version = parts[2];
 
-        let pom = match self.client.get_pom(group, artifact, version) {
-            Ok(p) => p,
-            Err(_) => return Ok(()),
-        };
+        let pom = self
+            .poms
+            .effective_pom(group, artifact, version)
+            .with_context(|| {
+                format!(
+                    "Failed to read the POM of {} (to install it without dependencies, declare it with \"pom\": false in jpkg.json)",
+                    key
+                )
+            })?;
+        if let Some(packaging) = &pom.packaging {
+            self.packaging
+                .insert(key.to_string(), packaging.trim().to_string());
+        }
 
+        let mut dependencies = Vec::new();
         for dep in pom.dependencies.dependency {
-            if let Some(scope) = &dep.scope {
-                if scope == "test" {
-                    continue;
+            // Test and provided dependencies aren't transitive, system ones aren't in any repository
+            let scope = match Scope::from_pom(dep.scope.as_deref()) {
+                Some(scope @ (Scope::Compile | Scope::Runtime)) if !dep.is_optional() => scope,
+                _ => continue,
+            };
+
+            // Versions managed by the manifest's BOMs override the POM's own
+            let artifact = dep.management_key();
+            let managed = self.managed.get(&artifact).cloned();
+            let Some(ver) = managed.or(dep.version) else {
+                anyhow::bail!(
+                    "The POM of {} declares {} without a version, and no dependencyManagement or BOM manages it",
+                    key,
+                    artifact
+                );
+            };
+            if let Some(property) = [&dep.group_id, &dep.artifact_id, &ver]
+                .into_iter()
+                .find_map(|value| unresolved_property(value))
+            {
+                anyhow::bail!(
+                    "The POM of {} declares {}:{} with the undefined property {}",
+                    key,
+                    artifact,
+                    ver,
+                    property
+                );
+            }
+            dependencies.push(Requirement {
+                artifact,
+                spec: ver,
+                scope,
+                classifier: dep.classifier,
+                exclusions: dep.exclusions.exclusion,
+            });
+        }
+        Ok(dependencies)
+    }
+
+    /// Variables for the versions of `artifact`, most preferred first, with
+    /// their distance from jpkg.json. Only jpkg.json and the versions `chosen`
+    /// so far (with their distances) count as asking for a version; versions
+    /// nobody chosen asks for come last, without a distance.
+    fn preferred(
+        &self,
+        artifact: &str,
+        chosen: &HashMap<String, usize>,
+    ) -> Vec<(Var, Option<usize>)> {
+        // (version, depth) of every request that counts
+        let mut requests: Vec<(&str, usize)> = Vec::new();
+        for ((root, _, _), keys) in self.roots.iter().zip(&self.root_keys) {
+            if root == artifact {
+                requests.extend(keys.iter().map(|key| (&key[root.len() + 1..], 0)));
+            }
+        }
+        for edge in self.edges.iter().filter(|e| e.artifact == artifact) {
+            let Some(&depth) = chosen.get(&edge.parent) else {
+                continue;
+            };
+            match &edge.allowed {
+                Some(allowed) => requests.extend(allowed.iter().map(|v| (v.as_str(), depth + 1))),
+                None => requests.push((&edge.spec, depth + 1)),
+            }
+        }
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        for (version, depth) in requests {
+            let entry = depths.entry(version).or_insert(depth);
+            *entry = (*entry).min(depth);
+        }
+
+        let mut candidates: Vec<(&str, Option<usize>)> = self.candidates[artifact]
+            .iter()
+            .map(|v| (v.as_str(), depths.get(v.as_str()).copied()))
+            .collect();
+        // Stable sorts: among equal candidates the first one asked for wins
+        match self.strategy {
+            ConflictStrategy::Nearest => {
+                candidates.sort_by_key(|(_, depth)| depth.unwrap_or(usize::MAX))
+            }
+            ConflictStrategy::Highest => candidates.sort_by(|a, b| {
+                (a.1.is_none().cmp(&b.1.is_none())).then_with(|| version::compare(b.0, a.0))
+            }),
+        }
+        candidates
+            .into_iter()
+            .map(|(v, depth)| (self.var_map[&format!("{}:{}", artifact, v)], depth))
+            .collect()
+    }
+
+    /// Whether an artifact that is not decided yet may still ask for `artifact`
+    fn awaits_requests(&self, artifact: &str, chosen: &HashMap<String, usize>) -> bool {
+        let decided: HashSet<&str> = chosen
+            .keys()
+            .filter_map(|key| key.rsplit_once(':'))
+            .map(|(artifact, _)| artifact)
+            .collect();
+        self.edges.iter().any(|edge| {
+            let parent = edge.parent.rsplit_once(':').map_or("", |(a, _)| a);
+            edge.artifact == artifact && parent != artifact && !decided.contains(parent)
+        })
+    }
+
+    fn versions(&self, artifact: &str) -> Vec<Var> {
+        self.candidates[artifact]
+            .iter()
+            .map(|v| self.var_map[&format!("{}:{}", artifact, v)])
+            .collect()
+    }
+
+    /// Guarded clause: `reason` requires `clause`
+    fn require(&mut self, reason: Reason, clause: &[Lit]) {
+        let selector = self.new_var();
+        self.selectors.push((selector, reason));
+        let mut guarded = vec![Lit::from_var(selector, false)];
+        guarded.extend_from_slice(clause);
+        self.solver.add_clause(&guarded);
+    }
+
+    /// Load the current graph into a fresh solver
+    fn add_constraints(&mut self) {
+        self.solver = Solver::new();
+        self.selectors.clear();
+
+        // jpkg.json -> its version, or one in its range
+        for index in 0..self.roots.len() {
+            let (artifact, spec, _) = &self.roots[index];
+            let reason = Reason::Root {
+                artifact: artifact.clone(),
+                spec: spec.clone(),
+            };
+            let clause: Vec<Lit> = self.root_keys[index]
+                .iter()
+                .map(|key| Lit::from_var(self.var_map[key], true))
+                .collect();
+            self.require(reason, &clause);
+        }
+
+        for index in 0..self.unreadable.len() {
+            let var = self.var_map[&self.unreadable[index].0];
+            self.require(Reason::Unreadable(index), &[Lit::from_var(var, false)]);
+        }
+
+        // Parent -> some allowed version of the child: !Parent v Child1 v Child2 ...
+        for index in 0..self.edges.len() {
+            let edge = &self.edges[index];
+            let mut clause = vec![Lit::from_var(self.var_map[&edge.parent], false)];
+            let versions = match &edge.allowed {
+                Some(allowed) => allowed
+                    .iter()
+                    .map(|v| self.var_map[&format!("{}:{}", edge.artifact, v)])
+                    .collect(),
+                None => self.versions(&edge.artifact),
+            };
+            clause.extend(versions.into_iter().map(|v| Lit::from_var(v, true)));
+            self.require(Reason::Edge(index), &clause);
+        }
+
+        // At most one version per artifact: !A v !B for every pair
+        for artifact in &self.artifacts {
+            let vars = self.versions(artifact);
+            for (i, &a) in vars.iter().enumerate() {
+                for &b in &vars[i + 1..] {
+                    self.solver
+                        .add_clause(&[Lit::from_var(a, false), Lit::from_var(b, false)]);
                 }
             }
+        }
+    }
+
+    fn satisfiable(&mut self, assumptions: &[Lit]) -> Result<bool> {
+        let mut all: Vec<Lit> = self
+            .selectors
+            .iter()
+            .map(|(selector, _)| Lit::from_var(*selector, true))
+            .collect();
+        all.extend_from_slice(assumptions);
+        self.solver.assume(&all);
+        Ok(self.solver.solve()?)
+    }
+
+    /// After an unsatisfiable solve: describe which requirements clash, e.g.
+    /// `app → g:a:1.0 → g:c:[2,3) conflicts with app → g:b:2.0 → g:c:1.5`
+    fn explain(&mut self) -> Result<anyhow::Error> {
+        let mut core: Vec<Lit> = self
+            .solver
+            .failed_core()
+            .map(|core| core.to_vec())
+            .unwrap_or_default();
+
+        // The solver's core need not be minimal; drop selectors it can do without
+        let mut i = 0;
+        while i < core.len() {
+            let mut trial = core.clone();
+            trial.remove(i);
+            self.solver.assume(&trial);
+            if self.solver.solve()? {
+                i += 1;
+            } else {
+                core = trial;
+            }
+        }
+
+        // In the order the requirements were found, so the output is stable
+        let reasons: Vec<&Reason> = self
+            .selectors
+            .iter()
+            .filter(|(selector, _)| core.iter().any(|lit| lit.var() == *selector))
+            .map(|(_, reason)| reason)
+            .collect();
+
+        // A version that can't be avoided, but whose POM can't be read
+        let unreadable = reasons.iter().find_map(|reason| match reason {
+            Reason::Unreadable(index) => Some(*index),
+            _ => None,
+        });
+        if let Some(index) = unreadable {
+            return Ok(anyhow::anyhow!("{}", self.unreadable[index].1));
+        }
+
+        // Group the clashing requirements by the artifact they constrain. Requirements
+        // that are alone on their artifact are just links in the chains shown.
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        for reason in &reasons {
+            let (artifact, description, impossible) = match reason {
+                Reason::Root { artifact, spec } => (
+                    artifact.clone(),
+                    format!("{} → {}:{}", self.manifest.package.name, artifact, spec),
+                    false,
+                ),
+                Reason::Edge(index) => {
+                    let edge = &self.edges[*index];
+                    (
+                        edge.artifact.clone(),
+                        format!(
+                            "{} → {}:{}",
+                            self.path_to(&edge.parent),
+                            edge.artifact,
+                            edge.spec
+                        ),
+                        edge.allowed.as_ref().is_some_and(|a| a.is_empty()),
+                    )
+                }
+                Reason::Unreadable(_) => continue,
+            };
+            let description = if impossible {
+                format!("{} (no published version matches)", description)
+            } else {
+                description
+            };
+            match groups.iter_mut().find(|(a, _)| *a == artifact) {
+                Some((_, descriptions)) => descriptions.push(description),
+                None => groups.push((artifact, vec![description])),
+            }
+        }
 
-            if let Some(ver) = dep.version {
-                if ver.contains("${") {
+        let mut lines: Vec<String> = groups
+            .iter()
+            .filter(|(_, descriptions)| {
+                descriptions.len() > 1 || descriptions[0].ends_with("matches)")
+            })
+            .map(|(_, descriptions)| descriptions.join("\n    conflicts with "))
+            .collect();
+        if lines.is_empty() {
+            lines = groups.into_iter().flat_map(|(_, d)| d).collect();
+        }
+
+        Ok(anyhow::anyhow!(
+            "Unsatisfiable dependencies:\n  {}",
+            lines.join("\n  ")
+        ))
+    }
+
+    /// Shortest chain from jpkg.json to `key`, e.g. `app → g:a:1.0 → g:b:2.0`
+    fn path_to(&self, key: &str) -> String {
+        let mut chain = vec![key.to_string()];
+        let mut current = key;
+        while let Some(parent) = self.reached_from.get(current) {
+            if chain.contains(parent) {
+                break;
+            }
+            chain.push(parent.clone());
+            current = parent;
+        }
+        chain.push(self.manifest.package.name.clone());
+        chain.reverse();
+        chain.join(" → ")
+    }
+
+    /// Pick one version for every artifact that is needed, following the
+    /// conflict strategy, and no version for artifacts nobody needs.
+    fn select(&mut self) -> Result<Vec<String>> {
+        self.add_constraints();
+
+        if !self.satisfiable(&[])? {
+            return Err(self.explain()?);
+        }
+
+        let mut fixed: Vec<Lit> = Vec::new();
+        // Selected versions and their distance from jpkg.json
+        let mut chosen: HashMap<String, usize> = HashMap::new();
+        let mut undecided = self.artifacts.clone();
+        // An artifact waits until every artifact that may ask for it is decided,
+        // unless nothing else can be (e.g. in a cycle)
+        let mut lenient = false;
+        loop {
+            let mut progress = false;
+            for artifact in std::mem::take(&mut undecided) {
+                let ranked = self.preferred(&artifact, &chosen);
+
+                // Not needed (yet): decide once the artifacts that need it are decided
+                let mut without: Vec<Lit> = fixed.clone();
+                without.extend(ranked.iter().map(|&(v, _)| Lit::from_var(v, false)));
+                if self.satisfiable(&without)? {
+                    undecided.push(artifact);
+                    continue;
+                }
+                if !lenient && self.awaits_requests(&artifact, &chosen) {
+                    undecided.push(artifact);
                     continue;
                 }
 
-                let dep_key = format!("{}:{}:{}", dep.group_id, dep.artifact_id, ver);
-                let dep_var = self.get_or_create_var(&dep_key);
+                let mut found = None;
+                for (var, depth) in ranked {
+                    let mut with = fixed.clone();
+                    with.push(Lit::from_var(var, true));
+                    if self.satisfiable(&with)? {
+                        found = Some((var, depth));
+                        break;
+                    }
+                }
+                let (var, depth) = found.context("Unsatisfiable dependencies")?;
+                fixed.push(Lit::from_var(var, true));
+                // Nothing selected asks for it, so it's further than anything that is
+                chosen.insert(
+                    self.rev_map[&var].clone(),
+                    depth.unwrap_or(self.artifacts.len()),
+                );
+                progress = true;
+                lenient = false;
+            }
+            if !progress {
+                if lenient || undecided.is_empty() {
+                    break;
+                }
+                lenient = true;
+            }
+        }
+
+        // Whatever is left is only needed by versions that were not selected
+        for artifact in &undecided {
+            fixed.extend(
+                self.versions(artifact)
+                    .into_iter()
+                    .map(|v| Lit::from_var(v, false)),
+            );
+        }
+        if !self.satisfiable(&fixed)? {
+            anyhow::bail!("Unsatisfiable dependencies")
+        }
 
-                // Implication: Parent -> Child
-                // !Parent v Child
-                // Lit::from_var(parent_var, false) is negative?
-                // varisat: Lit::from_var(var, true) is positive?
-                // Docs say: Lit::from_var(var, true) -> var
-                // Lit::from_var(var, false) -> !var
-                // Wait, let's check. Usually bool is "is_positive".
-                // If so, !Parent is Lit::from_var(parent_var, false).
-                // Child is Lit::from_var(dep_var, true).
+        let model = self.solver.model().context("Solver returned no model")?;
+        let mut resolved: Vec<String> = model
+            .iter()
+            .filter(|lit| lit.is_positive())
+            .filter_map(|lit| self.rev_map.get(&lit.var()).cloned())
+            .collect();
+        resolved.sort();
 
-                self.solver.add_clause(&[
-                    Lit::from_var(parent_var, false),
-                    Lit::from_var(dep_var, true),
-                ]);
+        self.selected = resolved
+            .iter()
+            .filter_map(|key| key.rsplit_once(':'))
+            .map(|(artifact, version)| (artifact.to_string(), version.to_string()))
+            .collect();
+
+        // Plain versions asked for by jpkg.json or by selected versions, that lost
+        let requests: Vec<(&String, &String)> = self
+            .roots
+            .iter()
+            .filter(|(_, spec, _)| !is_range(spec))
+            .map(|(artifact, spec, _)| (artifact, spec))
+            .chain(
+                self.edges
+                    .iter()
+                    .filter(|edge| edge.allowed.is_none() && chosen.contains_key(&edge.parent))
+                    .map(|edge| (&edge.artifact, &edge.spec)),
+            )
+            .collect();
+        self.conflicts = self
+            .artifacts
+            .iter()
+            .filter_map(|artifact| {
+                let selected = self.selected.get(artifact)?;
+                let mut rejected: Vec<String> = Vec::new();
+                for (_, spec) in requests.iter().filter(|(a, _)| *a == artifact) {
+                    if *spec != selected && !rejected.contains(spec) {
+                        rejected.push(spec.to_string());
+                    }
+                }
+                (!rejected.is_empty()).then(|| Conflict {
+                    artifact: artifact.clone(),
+                    selected: selected.clone(),
+                    rejected,
+                })
+            })
+            .collect();
+        self.assign_scopes();
+        Ok(resolved)
+    }
+
+    /// Give each selected artifact the widest scope along the edges that reach it
+    fn assign_scopes(&mut self) {
+        let selected = &self.selected;
+        let mut children: HashMap<&str, Vec<&Edge>> = HashMap::new();
+        for edge in &self.edges {
+            children.entry(edge.parent.as_str()).or_default().push(edge);
+        }
+
+        let mut scopes: HashMap<String, Scope> = HashMap::new();
+        let mut queue = VecDeque::new();
+        let reach = |artifact: &str,
+                     scope: Scope,
+                     scopes: &mut HashMap<String, Scope>,
+                     queue: &mut VecDeque<String>| {
+            let Some(version) = selected.get(artifact) else {
+                return;
+            };
+            let key = format!("{}:{}", artifact, version);
+            let widened = scopes.get(&key).map_or(scope, |s| s.widen(scope));
+            if scopes.get(&key) != Some(&widened) {
+                scopes.insert(key.clone(), widened);
+                queue.push_back(key);
             }
+        };
+
+        for (artifact, _, scope) in &self.roots {
+            reach(artifact, *scope, &mut scopes, &mut queue);
+        }
+        // Scopes only ever widen, so this settles
+        while let Some(key) = queue.pop_front() {
+            let Some(edges) = children.get(key.as_str()) else {
+                continue;
+            };
+            for edge in edges {
+                let scope = scopes[&key];
+                if let Some(scope) = scope.transitive(edge.scope) {
+                    reach(&edge.artifact, scope, &mut scopes, &mut queue);
+                }
+            }
+        }
+        self.scopes = scopes;
+    }
+}
+
+/// The first `${name}` left in `value` after interpolation
+fn unresolved_property(value: &str) -> Option<&str> {
+    let start = value.find("${")?;
+    let end = value[start..]
+        .find('}')
+        .map_or(value.len(), |end| start + end + 1);
+    Some(&value[start..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+
+    fn resolve_graph(
+        strategy: ConflictStrategy,
+        roots: &[(&str, &str)],
+        edges: &[(&str, &str, &str)],
+    ) -> Vec<String> {
+        let client = MemoryBackend::new();
+        let manifest = Manifest::new("app", "1.0");
+        let mut resolver = Resolver::new(&client, &manifest).with_strategy(strategy);
+        for (artifact, version) in roots {
+            resolver
+                .add_root(artifact, version, Scope::Compile)
+                .unwrap();
+        }
+        for (parent, artifact, version) in edges {
+            resolver
+                .add_edge(parent, artifact, version, Scope::Compile, None)
+                .unwrap();
+        }
+        resolver.select().unwrap()
+    }
+
+    // app -> a:1.0 -> guava:30.0, app -> b:1.0 -> c:1.0 -> guava:32.0
+    const GRAPH: &[(&str, &str, &str)] = &[
+        ("g:a:1.0", "g:guava", "30.0"),
+        ("g:b:1.0", "g:c", "1.0"),
+        ("g:c:1.0", "g:guava", "32.0"),
+        ("g:guava:32.0", "g:failureaccess", "1.0"),
+    ];
+
+    #[test]
+    fn test_nearest_wins() {
+        let resolved = resolve_graph(
+            ConflictStrategy::Nearest,
+            &[("g:a", "1.0"), ("g:b", "1.0")],
+            GRAPH,
+        );
+        assert_eq!(
+            resolved,
+            vec!["g:a:1.0", "g:b:1.0", "g:c:1.0", "g:guava:30.0"]
+        );
+    }
+
+    #[test]
+    fn test_highest_wins() {
+        let resolved = resolve_graph(
+            ConflictStrategy::Highest,
+            &[("g:a", "1.0"), ("g:b", "1.0")],
+            GRAPH,
+        );
+        assert_eq!(
+            resolved,
+            vec![
+                "g:a:1.0",
+                "g:b:1.0",
+                "g:c:1.0",
+                "g:failureaccess:1.0",
+                "g:guava:32.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only_selected_parents_rank_candidates() {
+        // app -> a:[1.0,2.0) picks a:1.5, so only b:1.0 asks for x
+        for strategy in [ConflictStrategy::Nearest, ConflictStrategy::Highest] {
+            let client = MemoryBackend::new();
+            let manifest = Manifest::new("app", "1.0");
+            let mut resolver = Resolver::new(&client, &manifest).with_strategy(strategy);
+            resolver.available.insert(
+                "g:a".to_string(),
+                vec!["1.0".to_string(), "1.5".to_string()],
+            );
+            resolver
+                .add_root("g:a", "[1.0,2.0)", Scope::Compile)
+                .unwrap();
+            resolver.add_root("g:b", "1.0", Scope::Compile).unwrap();
+            resolver
+                .add_edge("g:a:1.0", "g:x", "2.0", Scope::Compile, None)
+                .unwrap();
+            resolver
+                .add_edge("g:b:1.0", "g:x", "1.0", Scope::Compile, None)
+                .unwrap();
+
+            assert_eq!(
+                resolver.select().unwrap(),
+                vec!["g:a:1.5", "g:b:1.0", "g:x:1.0"],
+                "{}",
+                strategy
+            );
+            assert!(resolver.conflicts().is_empty());
         }
-        Ok(())
+    }
+
+    #[test]
+    fn test_direct_dependency_always_wins() {
+        let resolved = resolve_graph(
+            ConflictStrategy::Highest,
+            &[("g:a", "1.0"), ("g:guava", "20.0")],
+            &[("g:a:1.0", "g:guava", "30.0")],
+        );
+        assert_eq!(resolved, vec!["g:a:1.0", "g:guava:20.0"]);
+    }
+
+    #[test]
+    fn test_scopes_follow_the_widest_path() {
+        let client = MemoryBackend::new();
+        let manifest = Manifest::new("app", "1.0");
+        let mut resolver = Resolver::new(&client, &manifest);
+
+        // junit is test-only; slf4j-api is needed by both a test and a compile path
+        resolver.add_root("g:a", "1.0", Scope::Compile).unwrap();
+        resolver
+            .add_root("junit:junit", "4.13", Scope::Test)
+            .unwrap();
+        resolver
+            .add_root("g:servlet", "6.0", Scope::Provided)
+            .unwrap();
+        resolver
+            .add_edge(
+                "junit:junit:4.13",
+                "g:hamcrest",
+                "1.3",
+                Scope::Compile,
+                None,
+            )
+            .unwrap();
+        resolver
+            .add_edge(
+                "junit:junit:4.13",
+                "g:slf4j-api",
+                "2.0",
+                Scope::Compile,
+                None,
+            )
+            .unwrap();
+        resolver
+            .add_edge("g:a:1.0", "g:driver", "1.0", Scope::Runtime, None)
+            .unwrap();
+        resolver
+            .add_edge("g:driver:1.0", "g:slf4j-api", "2.0", Scope::Compile, None)
+            .unwrap();
+        resolver
+            .add_edge(
+                "g:servlet:6.0",
+                "g:annotations",
+                "1.0",
+                Scope::Compile,
+                None,
+            )
+            .unwrap();
+        resolver.select().unwrap();
+
+        assert_eq!(resolver.scope("g:a:1.0"), Scope::Compile);
+        assert_eq!(resolver.scope("junit:junit:4.13"), Scope::Test);
+        assert_eq!(resolver.scope("g:hamcrest:1.3"), Scope::Test);
+        assert_eq!(resolver.scope("g:driver:1.0"), Scope::Runtime);
+        assert_eq!(resolver.scope("g:slf4j-api:2.0"), Scope::Runtime);
+        assert_eq!(resolver.scope("g:servlet:6.0"), Scope::Provided);
+        assert_eq!(resolver.scope("g:annotations:1.0"), Scope::Provided);
+    }
+
+    #[test]
+    fn test_range_excludes_nearer_version() {
+        let client = MemoryBackend::new();
+        let manifest = Manifest::new("app", "1.0");
+        let mut resolver = Resolver::new(&client, &manifest);
+        resolver.available.insert(
+            "g:c".to_string(),
+            vec!["1.5".to_string(), "2.0".to_string(), "2.5".to_string()],
+        );
+
+        // app -> c:1.5 is nearer, but a:1.0 only accepts c in [2,3)
+        resolver.add_root("g:a", "1.0", Scope::Compile).unwrap();
+        resolver.add_root("g:b", "1.0", Scope::Compile).unwrap();
+        resolver
+            .add_edge("g:b:1.0", "g:c", "1.5", Scope::Compile, None)
+            .unwrap();
+        resolver
+            .add_edge("g:a:1.0", "g:c", "[2,3)", Scope::Compile, None)
+            .unwrap();
+
+        let resolved = resolver.select().unwrap();
+        assert_eq!(resolved, vec!["g:a:1.0", "g:b:1.0", "g:c:2.5"]);
+        assert_eq!(resolver.conflicts()[0].rejected, vec!["1.5"]);
+    }
+
+    #[test]
+    fn test_root_range_picks_highest_match() {
+        let client = MemoryBackend::new();
+        let manifest = Manifest::new("app", "1.0");
+        let mut resolver = Resolver::new(&client, &manifest);
+        resolver.available.insert(
+            "g:a".to_string(),
+            vec!["1.0".to_string(), "1.9".to_string(), "2.0".to_string()],
+        );
+
+        resolver
+            .add_root("g:a", "[1.0,2.0)", Scope::Compile)
+            .unwrap();
+        assert_eq!(resolver.select().unwrap(), vec!["g:a:1.9"]);
+        assert!(resolver.add_root("g:a", "[3.0,)", Scope::Compile).is_err());
+    }
+
+    #[test]
+    fn test_explain_conflicting_ranges() {
+        let client = MemoryBackend::new();
+        let manifest = Manifest::new("app", "1.0");
+        let mut resolver = Resolver::new(&client, &manifest);
+        resolver.available.insert(
+            "g:c".to_string(),
+            vec!["1.5".to_string(), "2.0".to_string()],
+        );
+
+        resolver.add_root("g:a", "1.0", Scope::Compile).unwrap();
+        resolver.add_root("g:b", "2.0", Scope::Compile).unwrap();
+        resolver
+            .add_edge("g:a:1.0", "g:x", "1.0", Scope::Compile, None)
+            .unwrap();
+        resolver
+            .add_edge("g:x:1.0", "g:c", "[2,3)", Scope::Compile, None)
+            .unwrap();
+        resolver
+            .add_edge("g:b:2.0", "g:c", "[1.5]", Scope::Compile, None)
+            .unwrap();
+
+        let message = resolver.select().unwrap_err().to_string();
+        assert!(
+            message.contains(
+                "app → g:a:1.0 → g:x:1.0 → g:c:[2,3)\n    conflicts with app → g:b:2.0 → g:c:[1.5]"
+            ),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_explain_empty_range() {
+        let client = MemoryBackend::new();
+        let manifest = Manifest::new("app", "1.0");
+        let mut resolver = Resolver::new(&client, &manifest);
+        resolver
+            .available
+            .insert("g:c".to_string(), vec!["1.0".to_string()]);
+
+        resolver.add_root("g:a", "1.0", Scope::Compile).unwrap();
+        resolver
+            .add_edge("g:a:1.0", "g:c", "[2,)", Scope::Compile, None)
+            .unwrap();
+
+        let message = resolver.select().unwrap_err().to_string();
+        assert!(
+            message.contains("app → g:a:1.0 → g:c:[2,) (no published version matches)"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_resolve_from_poms() {
+        // app -> a:1.0 -> c:1.0, app -> b:1.0 -> d:1.0 -> (c:1.5, e:[1.0,2.0))
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &["g:c:1.0"])
+            .with_artifact("g:b:1.0", &["g:d:1.0"])
+            .with_artifact("g:d:1.0", &["g:c:1.5", "g:e:[1.0,2.0)"])
+            .with_artifact("g:c:1.0", &[])
+            .with_artifact("g:c:1.5", &[])
+            .with_artifact("g:e:1.0", &[])
+            .with_artifact("g:e:1.5", &[])
+            .with_artifact("g:e:2.0", &[]);
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0", "g:b": "1.0"}
+            }"#,
+        )
+        .unwrap();
+
+        let mut resolver = Resolver::new(&backend, &manifest);
+        let mut resolved = resolver.resolve().unwrap();
+        resolved.sort();
+        assert_eq!(
+            resolved,
+            vec!["g:a:1.0", "g:b:1.0", "g:c:1.0", "g:d:1.0", "g:e:1.5"]
+        );
+
+        let mut resolver =
+            Resolver::new(&backend, &manifest).with_strategy(ConflictStrategy::Highest);
+        let mut resolved = resolver.resolve().unwrap();
+        resolved.sort();
+        assert_eq!(
+            resolved,
+            vec!["g:a:1.0", "g:b:1.0", "g:c:1.5", "g:d:1.0", "g:e:1.5"]
+        );
+    }
+
+    #[test]
+    fn test_range_skips_unreadable_poms() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0"}
+            }"#,
+        )
+        .unwrap();
+
+        // Only the selected version's POM is read
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &["g:c:[1.0,2.0)"])
+            .with_file("g/c/1.0/c-1.0.pom", "<project>")
+            .with_artifact("g:c:1.5", &[]);
+        let resolved = Resolver::new(&backend, &manifest).resolve().unwrap();
+        assert_eq!(resolved, vec!["g:a:1.0", "g:c:1.5"]);
+
+        // An unreadable POM rules its version out
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &["g:c:[1.0,2.0)"])
+            .with_artifact("g:c:1.0", &[])
+            .with_file("g/c/1.5/c-1.5.pom", "<project>");
+        let resolved = Resolver::new(&backend, &manifest).resolve().unwrap();
+        assert_eq!(resolved, vec!["g:a:1.0", "g:c:1.0"]);
+    }
+
+    #[test]
+    fn test_unselected_pom_is_not_read() {
+        // app -> b:2.0 wins over app -> a:1.0 -> b:1.0, whose POM is broken
+        let backend = MemoryBackend::new()
+            .with_artifact("g:a:1.0", &["g:b:1.0"])
+            .with_file("g/b/1.0/b-1.0.pom", "<project>")
+            .with_artifact("g:b:2.0", &[]);
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0", "g:b": "2.0"}
+            }"#,
+        )
+        .unwrap();
+
+        let resolved = Resolver::new(&backend, &manifest).resolve().unwrap();
+        assert_eq!(resolved, vec!["g:a:1.0", "g:b:2.0"]);
+    }
+
+    #[test]
+    fn test_missing_pom_fails() {
+        let backend = MemoryBackend::new().with_artifact("g:a:1.0", &["g:b:1.0"]);
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0"}
+            }"#,
+        )
+        .unwrap();
+
+        let message = format!(
+            "{:#}",
+            Resolver::new(&backend, &manifest).resolve().unwrap_err()
+        );
+        assert!(
+            message.contains("Failed to read the POM of g:b:1.0"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_unresolved_dependency_fails() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0"}
+            }"#,
+        )
+        .unwrap();
+        let pom = |dependency: &str| {
+            format!(
+                "<project><groupId>g</groupId><artifactId>a</artifactId><version>1.0</version><dependencies><dependency>{}</dependency></dependencies></project>",
+                dependency
+            )
+        };
+        let error = |dependency: &str| {
+            let backend = MemoryBackend::new().with_file("g/a/1.0/a-1.0.pom", pom(dependency));
+            format!(
+                "{:#}",
+                Resolver::new(&backend, &manifest).resolve().unwrap_err()
+            )
+        };
+
+        let message =
+            error("<groupId>g</groupId><artifactId>b</artifactId><version>${b.version}</version>");
+        assert!(
+            message.contains(
+                "The POM of g:a:1.0 declares g:b:${b.version} with the undefined property ${b.version}"
+            ),
+            "{}",
+            message
+        );
+
+        let message = error("<groupId>g</groupId><artifactId>b</artifactId>");
+        assert!(
+            message.contains("The POM of g:a:1.0 declares g:b without a version"),
+            "{}",
+            message
+        );
     }
 }
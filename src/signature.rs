@@ -0,0 +1,224 @@
+use crate::manifest::SignaturePolicy;
+use anyhow::Result;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A signature that is missing, bad or by the wrong key
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct SignatureError(pub String);
+
+/// A verified `.asc` signature and the fingerprint of the primary key that made it
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub asc: Vec<u8>,
+    pub signer: String,
+}
+
+/// Checks PGP signatures with gpg, against the keys a `SignaturePolicy` trusts.
+/// Keys are never fetched, so verification works offline once they're imported.
+pub struct Verifier {
+    policy: SignaturePolicy,
+}
+
+impl Verifier {
+    pub fn new(policy: SignaturePolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Verify `asc` over `data`, a file of an artifact of `group` that `source`
+    /// names in errors
+    pub fn verify(&self, group: &str, data: &[u8], asc: &[u8], source: &str) -> Result<Signature> {
+        let signer = self
+            .gpg_verify(data, asc)
+            .and_then(|status| parse_status(&status))
+            .map_err(|e| SignatureError(format!("Signature check failed for {}: {}", source, e)))?;
+        self.check_signer(group, &signer, source)?;
+        Ok(Signature {
+            asc: asc.to_vec(),
+            signer,
+        })
+    }
+
+    /// Fail if keys are pinned for `group` and `signer` is not one of them
+    pub fn check_signer(&self, group: &str, signer: &str, source: &str) -> Result<()> {
+        let Some(pinned) = self.policy.pinned_keys(group) else {
+            return Ok(());
+        };
+        if !pinned.iter().any(|key| same_fingerprint(key, signer)) {
+            return Err(SignatureError(format!(
+                "{} is signed by key {}, which is not pinned for {} in jpkg.json",
+                source, signer, group
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Run `gpg --verify` with the data on stdin; returns gpg's status output
+    fn gpg_verify(&self, data: &[u8], asc: &[u8]) -> Result<String> {
+        // gpg needs the detached signature as a file
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let asc_path = std::env::temp_dir().join(format!(
+            "jpkg-{}-{}.asc",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&asc_path, asc)?;
+
+        let mut command = Command::new("gpg");
+        command.args(["--batch", "--no-tty", "--status-fd", "1"]);
+        if let Some(keyring) = self.keyring() {
+            command.arg("--homedir").arg(keyring);
+        }
+        command
+            .arg("--verify")
+            .arg(&asc_path)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        let output = command.spawn().and_then(|mut child| {
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            let data = data.to_vec();
+            // Feed stdin from another thread so gpg can't block us on a full stdout
+            let writer = std::thread::spawn(move || stdin.write_all(&data));
+            let output = child.wait_with_output();
+            // gpg may stop reading early (e.g. an unknown key); its status tells why
+            let _ = writer.join();
+            output
+        });
+        let _ = std::fs::remove_file(&asc_path);
+
+        match output {
+            Ok(output) => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!("gpg not found; install GnuPG to verify signatures")
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn keyring(&self) -> Option<PathBuf> {
+        let keyring = self.policy.keyring.as_deref()?;
+        match keyring.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+            None => Some(PathBuf::from(keyring)),
+        }
+    }
+}
+
+/// Fingerprint of the primary key of a good signature, from gpg's `--status-fd` output
+fn parse_status(status: &str) -> Result<String> {
+    let mut signer = None;
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[..] {
+            // VALIDSIG <fingerprint> <date> <timestamp> <expires> <version> <reserved>
+            //          <pubkey-algo> <hash-algo> <class> <primary-fingerprint>
+            ["VALIDSIG", fingerprint, ref rest @ ..] => {
+                signer = Some(rest.get(8).copied().unwrap_or(fingerprint).to_string())
+            }
+            ["BADSIG", key, ..] => anyhow::bail!("bad signature from key {}", key),
+            ["REVKEYSIG", key, ..] => anyhow::bail!("key {} has been revoked", key),
+            ["EXPKEYSIG", key, ..] => anyhow::bail!("key {} has expired", key),
+            ["EXPSIG", key, ..] => anyhow::bail!("signature by key {} has expired", key),
+            ["NO_PUBKEY", key] => anyhow::bail!(
+                "public key {} is not in the keyring (import it with 'gpg --recv-keys {}')",
+                key,
+                key
+            ),
+            _ => {}
+        }
+    }
+    signer.ok_or_else(|| anyhow::anyhow!("no valid signature"))
+}
+
+/// Compare fingerprints written with or without spaces, in either case
+fn same_fingerprint(a: &str, b: &str) -> bool {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_uppercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const PRIMARY: &str = "EE0CA873074092F806F59B65D364ABAA39A47320";
+
+    #[test]
+    fn test_parse_gpg_status() {
+        let good = format!(
+            "[GNUPG:] NEWSIG\n\
+             [GNUPG:] GOODSIG D364ABAA39A47320 Guava Release <guava@example.com>\n\
+             [GNUPG:] VALIDSIG 1111111111111111111111111111111111111111 2024-01-01 1704067200 0 4 0 1 10 00 {}\n",
+            PRIMARY
+        );
+        assert_eq!(parse_status(&good).unwrap(), PRIMARY);
+
+        let err = parse_status("[GNUPG:] ERRSIG D364ABAA39A47320 1 10 00 1704067200 9 -\n[GNUPG:] NO_PUBKEY D364ABAA39A47320\n")
+            .unwrap_err();
+        assert!(err.to_string().contains("gpg --recv-keys D364ABAA39A47320"));
+        assert!(parse_status("[GNUPG:] BADSIG D364ABAA39A47320 Guava\n").is_err());
+        assert!(parse_status("").is_err());
+    }
+
+    #[test]
+    fn test_parse_expired_status() {
+        // gpg still reports VALIDSIG for these; the expiry must win
+        let validsig = format!(
+            "[GNUPG:] VALIDSIG {} 2020-01-01 1577836800 0 4 0 1 10 00 {}\n",
+            PRIMARY, PRIMARY
+        );
+        let expired_key = format!(
+            "[GNUPG:] EXPKEYSIG D364ABAA39A47320 Guava Release <guava@example.com>\n{}",
+            validsig
+        );
+        let err = parse_status(&expired_key).unwrap_err();
+        assert_eq!(err.to_string(), "key D364ABAA39A47320 has expired");
+
+        let expired_signature = format!(
+            "[GNUPG:] EXPSIG D364ABAA39A47320 Guava Release <guava@example.com>\n{}",
+            validsig
+        );
+        let err = parse_status(&expired_signature).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "signature by key D364ABAA39A47320 has expired"
+        );
+    }
+
+    #[test]
+    fn test_pinned_signers() {
+        let verifier = Verifier::new(SignaturePolicy {
+            keyring: None,
+            keys: BTreeMap::from([(
+                "com.google.*".to_string(),
+                vec!["ee0c a873 0740 92f8 06f5  9b65 d364 abaa 39a4 7320".to_string()],
+            )]),
+        });
+        assert!(
+            verifier
+                .check_signer("com.google.guava", PRIMARY, "guava")
+                .is_ok()
+        );
+        let err = verifier
+            .check_signer("com.google.guava", "0000", "guava-33.0.jar")
+            .unwrap_err();
+        assert!(err.to_string().contains("not pinned for com.google.guava"));
+        // Any key in the keyring may sign other groups
+        assert!(verifier.check_signer("org.json", "0000", "json").is_ok());
+    }
+}
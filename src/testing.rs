@@ -1,3 +1,4 @@
+use crate::platform;
 use anyhow::Result;
 use colored::Colorize;
 use std::fs;
@@ -26,9 +27,10 @@ pub fn run_tests(verbose: bool) -> Result<()> {
 
     fs::create_dir_all("bin/test")?;
 
+    let classpath = platform::build_classpath(platform::TEST_CLASSPATH);
     let mut cmd = Command::new("javac");
     cmd.arg("-d").arg("bin/test");
-    cmd.arg("-cp").arg("lib/*:bin"); // Include main classes and libs
+    cmd.arg("-cp").arg(&classpath); // Include main classes and libs of every scope
     cmd.arg("-sourcepath").arg("src/test/java");
 
     for file in &test_files {
@@ -66,7 +68,7 @@ pub fn run_tests(verbose: bool) -> Result<()> {
 
             let result = Command::new("java")
                 .arg("-cp")
-                .arg("bin/test:bin:lib/*")
+                .arg(&classpath)
                 .arg(&class_name)
                 .output()?;
 
@@ -107,10 +109,8 @@ fn visit_dirs(dir: &Path, cb: &mut Vec<std::path::PathBuf>) -> Result<()> {
             let path = entry.path();
             if path.is_dir() {
                 visit_dirs(&path, cb)?;
-            } else if let Some(ext) = path.extension() {
-                if ext == "java" {
-                    cb.push(path);
-                }
+            } else if path.extension().is_some_and(|ext| ext == "java") {
+                cb.push(path);
             }
         }
     }
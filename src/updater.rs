@@ -1,59 +1,73 @@
-use crate::maven::MavenClient;
+use crate::backend::RepositoryBackend;
+use crate::installer;
+use crate::lockfile::LockFile;
+use crate::manifest::Manifest;
+use crate::version::{self, is_range};
 use anyhow::Result;
 use colored::Colorize;
+use std::fs;
+use std::path::Path;
 
 pub fn check_updates() -> Result<()> {
-    use crate::manifest::Manifest;
-    use std::fs;
-    use std::path::Path;
-
-    if !Path::new("jpkg.json").exists() {
-        anyhow::bail!("{}", "jpkg.json not found".red());
-    }
-
-    let content = fs::read_to_string("jpkg.json")?;
-    let manifest: Manifest = serde_json::from_str(&content)?;
+    let manifest = load_manifest()?;
+    let client = installer::repository_client(&manifest, &LockFile::load()?)?;
+    report_updates(&client, &manifest)?;
+    Ok(())
+}
 
-    if manifest.dependencies.is_empty() {
+/// Print which dependencies of `manifest` have a newer version in `backend`;
+/// returns whether any has
+fn report_updates(backend: &dyn RepositoryBackend, manifest: &Manifest) -> Result<bool> {
+    if manifest.all_dependencies().next().is_none() {
         println!("{}", "No dependencies to check".dimmed());
-        return Ok(());
+        return Ok(false);
     }
 
     println!("{}", "🔍 Checking for updates...".cyan());
 
-    let client = MavenClient::new();
     let mut updates_available = false;
 
-    for (key, current_version) in &manifest.dependencies {
+    for (key, spec, _) in manifest.all_dependencies() {
+        let current_version = spec.version();
         let parts: Vec<&str> = key.split(':').collect();
         if parts.len() != 2 {
             continue;
         }
 
-        let artifact = parts[1];
-
-        // Search for latest version
-        match client.search_artifact(artifact) {
-            Ok(results) => {
-                if let Some(result) = results.iter().find(|r| r.a == artifact && r.g == parts[0]) {
-                    if &result.latest_version != current_version {
-                        updates_available = true;
-                        println!(
-                            "  {} {} {} → {}",
-                            "↑".yellow(),
-                            key,
-                            current_version.dimmed(),
-                            result.latest_version.green()
-                        );
-                    } else {
-                        println!("  {} {} {}", "✓".green(), key, current_version.dimmed());
-                    }
-                } else {
-                    println!("  {} {} (not found)", "?".yellow(), key);
-                }
+        // Versionless dependencies follow their BOM
+        if current_version.is_empty() {
+            println!("  {} {} (managed by BOM)", "✓".green(), key);
+            continue;
+        }
+        if is_range(current_version) {
+            println!(
+                "  {} {} {} (range)",
+                "✓".green(),
+                key,
+                current_version.dimmed()
+            );
+            continue;
+        }
+
+        match latest_version(backend, key) {
+            Ok(Some(latest)) if latest != current_version => {
+                updates_available = true;
+                println!(
+                    "  {} {} {} → {}",
+                    "↑".yellow(),
+                    key,
+                    current_version.dimmed(),
+                    latest.green()
+                );
+            }
+            Ok(Some(_)) => {
+                println!("  {} {} {}", "✓".green(), key, current_version.dimmed());
+            }
+            Ok(None) => {
+                println!("  {} {} (not found)", "?".yellow(), key);
             }
             Err(_) => {
-                println!("  {} {} (search failed)", "✗".red(), key);
+                println!("  {} {} (lookup failed)", "✗".red(), key);
             }
         }
     }
@@ -66,52 +80,68 @@ pub fn check_updates() -> Result<()> {
         println!("{}", "Run 'jpkg update' to update dependencies".dimmed());
     }
 
-    Ok(())
+    Ok(updates_available)
 }
 
 pub fn update_dependencies(package: Option<String>) -> Result<()> {
-    use crate::manifest::Manifest;
-    use std::fs;
-    use std::path::Path;
+    let mut manifest = load_manifest()?;
+    let client = installer::repository_client(&manifest, &LockFile::load()?)?;
 
-    if !Path::new("jpkg.json").exists() {
-        anyhow::bail!("{}", "jpkg.json not found".red());
+    if update_manifest(&client, &mut manifest, package.as_deref())? {
+        let content = serde_json::to_string_pretty(&manifest)?;
+        fs::write("jpkg.json", content)?;
+        println!();
+        println!("{}", "✓ Dependencies updated in jpkg.json".green().bold());
+        println!(
+            "{}",
+            "Run 'jpkg install' to download updated packages".dimmed()
+        );
+    } else {
+        println!("{}", "No updates available".dimmed());
     }
 
-    let content = fs::read_to_string("jpkg.json")?;
-    let mut manifest: Manifest = serde_json::from_str(&content)?;
+    Ok(())
+}
 
-    let client = MavenClient::new();
+/// Move `package`, or every dependency, of `manifest` to its latest version in
+/// `backend`; returns whether anything changed
+fn update_manifest(
+    backend: &dyn RepositoryBackend,
+    manifest: &mut Manifest,
+    package: Option<&str>,
+) -> Result<bool> {
     let mut updated = false;
 
     if let Some(pkg) = package {
         // Update specific package
-        if let Some(current_version) = manifest.dependencies.get(&pkg) {
-            let parts: Vec<&str> = pkg.split(':').collect();
-            if parts.len() == 2 {
-                match client.search_artifact(parts[1]) {
-                    Ok(results) => {
-                        if let Some(result) =
-                            results.iter().find(|r| r.a == parts[1] && r.g == parts[0])
-                        {
-                            if &result.latest_version != current_version {
-                                manifest
-                                    .dependencies
-                                    .insert(pkg.clone(), result.latest_version.clone());
-                                println!(
-                                    "{}",
-                                    format!("✓ Updated {} to {}", pkg, result.latest_version)
-                                        .green()
-                                );
-                                updated = true;
-                            } else {
-                                println!("{}", format!("{} is already up to date", pkg).dimmed());
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        anyhow::bail!("Failed to check updates for {}: {}", pkg, e);
-                    }
+        if let Some(current_version) = manifest
+            .dependency_mut(pkg)
+            .map(|d| d.version().to_string())
+        {
+            if current_version.is_empty() {
+                anyhow::bail!(
+                    "{}",
+                    format!("{} is managed by a BOM; update the BOM instead", pkg).red()
+                );
+            }
+            if is_range(&current_version) {
+                anyhow::bail!(
+                    "{}",
+                    format!("{} uses the version range {}", pkg, current_version).red()
+                );
+            }
+            match latest_version(backend, pkg) {
+                Ok(Some(latest)) if latest != current_version => {
+                    manifest.add_dependency(pkg, &latest);
+                    println!("{}", format!("✓ Updated {} to {}", pkg, latest).green());
+                    updated = true;
+                }
+                Ok(Some(_)) => {
+                    println!("{}", format!("{} is already up to date", pkg).dimmed());
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    anyhow::bail!("Failed to check updates for {}: {}", pkg, e);
                 }
             }
         } else {
@@ -121,36 +151,31 @@ pub fn update_dependencies(package: Option<String>) -> Result<()> {
         // Update all packages
         println!("{}", "🔄 Updating all dependencies...".cyan());
 
-        let deps: Vec<_> = manifest.dependencies.keys().cloned().collect();
+        let deps: Vec<_> = manifest
+            .all_dependencies()
+            .map(|(key, spec, _)| (key.clone(), spec.version().to_string()))
+            .collect();
 
-        for key in deps {
-            let current_version = manifest.dependencies.get(&key).unwrap().clone();
+        for (key, current_version) in deps {
             let parts: Vec<&str> = key.split(':').collect();
 
-            if parts.len() != 2 {
+            if parts.len() != 2 || current_version.is_empty() || is_range(&current_version) {
                 continue;
             }
 
-            match client.search_artifact(parts[1]) {
-                Ok(results) => {
-                    if let Some(result) =
-                        results.iter().find(|r| r.a == parts[1] && r.g == parts[0])
-                    {
-                        if result.latest_version != current_version {
-                            manifest
-                                .dependencies
-                                .insert(key.clone(), result.latest_version.clone());
-                            println!(
-                                "  {} {} {} → {}",
-                                "↑".yellow(),
-                                key,
-                                current_version.dimmed(),
-                                result.latest_version.green()
-                            );
-                            updated = true;
-                        }
-                    }
+            match latest_version(backend, &key) {
+                Ok(Some(latest)) if latest != current_version => {
+                    manifest.add_dependency(&key, &latest);
+                    println!(
+                        "  {} {} {} → {}",
+                        "↑".yellow(),
+                        key,
+                        current_version.dimmed(),
+                        latest.green()
+                    );
+                    updated = true;
                 }
+                Ok(_) => {}
                 Err(_) => {
                     println!("  {} {} (update failed)", "✗".red(), key);
                 }
@@ -158,18 +183,88 @@ pub fn update_dependencies(package: Option<String>) -> Result<()> {
         }
     }
 
-    if updated {
-        let content = serde_json::to_string_pretty(&manifest)?;
-        fs::write("jpkg.json", content)?;
-        println!();
-        println!("{}", "✓ Dependencies updated in jpkg.json".green().bold());
-        println!(
-            "{}",
-            "Run 'jpkg install' to download updated packages".dimmed()
+    Ok(updated)
+}
+
+fn load_manifest() -> Result<Manifest> {
+    if !Path::new("jpkg.json").exists() {
+        anyhow::bail!("{}", "jpkg.json not found".red());
+    }
+
+    let content = fs::read_to_string("jpkg.json")?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Latest release of `group:artifact` in `backend`'s repositories, or `None`
+/// if it has no versions
+fn latest_version(backend: &dyn RepositoryBackend, key: &str) -> Result<Option<String>> {
+    let Some((group, artifact)) = key.split_once(':') else {
+        return Ok(None);
+    };
+    let versioning = backend.get_metadata(group, artifact)?.versioning;
+    Ok(versioning.release.or_else(|| {
+        versioning
+            .versions
+            .version
+            .into_iter()
+            .filter(|v| !v.ends_with("-SNAPSHOT"))
+            .max_by(|a, b| version::compare(a, b))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+
+    fn manifest() -> Manifest {
+        serde_json::from_str(
+            r#"{
+                "package": {"name": "app", "version": "1.0"},
+                "dependencies": {"g:a": "1.0", "g:b": "2.0", "g:c": "[1.0,2.0)"}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn backend() -> MemoryBackend {
+        MemoryBackend::new()
+            .with_artifact("g:a:1.0", &[])
+            .with_artifact("g:a:1.1", &[])
+            .with_artifact("g:b:2.0", &[])
+            .with_artifact("g:c:1.0", &[])
+            .with_artifact("g:c:3.0", &[])
+    }
+
+    #[test]
+    fn test_report_updates() {
+        assert!(report_updates(&backend(), &manifest()).unwrap());
+        let current = MemoryBackend::new().with_artifact("g:a:1.0", &[]);
+        assert!(!report_updates(&current, &manifest()).unwrap());
+    }
+
+    #[test]
+    fn test_update_all() {
+        let mut manifest = manifest();
+        assert!(update_manifest(&backend(), &mut manifest, None).unwrap());
+        assert_eq!(manifest.dependency_mut("g:a").unwrap().version(), "1.1");
+        assert_eq!(manifest.dependency_mut("g:b").unwrap().version(), "2.0");
+        // Ranges are left alone
+        assert_eq!(
+            manifest.dependency_mut("g:c").unwrap().version(),
+            "[1.0,2.0)"
         );
-    } else {
-        println!("{}", "No updates available".dimmed());
     }
 
-    Ok(())
+    #[test]
+    fn test_update_one() {
+        let mut manifest = manifest();
+        assert!(!update_manifest(&backend(), &mut manifest, Some("g:b")).unwrap());
+        assert!(update_manifest(&backend(), &mut manifest, Some("g:a")).unwrap());
+        assert_eq!(manifest.dependency_mut("g:a").unwrap().version(), "1.1");
+
+        let err = update_manifest(&backend(), &mut manifest, Some("g:c")).unwrap_err();
+        assert!(err.to_string().contains("uses the version range"));
+        assert!(update_manifest(&backend(), &mut manifest, Some("g:x")).is_err());
+    }
 }
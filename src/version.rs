@@ -0,0 +1,290 @@
+//! Maven version ordering (a simplified take on Maven's ComparableVersion)
+
+use std::cmp::Ordering;
+
+#[derive(Debug, PartialEq)]
+enum Item {
+    Number(u64),
+    Qualifier(String),
+}
+
+/// Rank of a release with no qualifier, e.g. "1.0" (also "1.0-ga", "1.0-final")
+const RELEASE_RANK: usize = 5;
+
+fn qualifier_rank(qualifier: &str) -> usize {
+    match qualifier {
+        "alpha" | "a" => 0,
+        "beta" | "b" => 1,
+        "milestone" | "m" => 2,
+        "rc" | "cr" => 3,
+        "snapshot" => 4,
+        "" | "ga" | "final" | "release" => RELEASE_RANK,
+        "sp" => 6,
+        _ => 7,
+    }
+}
+
+fn flush(current: &mut String, digits: bool, items: &mut Vec<Item>) {
+    if current.is_empty() {
+        return;
+    }
+    let item = if digits {
+        Item::Number(current.parse().unwrap_or(u64::MAX))
+    } else {
+        Item::Qualifier(current.to_lowercase())
+    };
+    items.push(item);
+    current.clear();
+}
+
+fn tokenize(version: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut digits = false;
+
+    for c in version.chars() {
+        if c == '.' || c == '-' || c == '_' {
+            flush(&mut current, digits, &mut items);
+        } else {
+            let is_digit = c.is_ascii_digit();
+            // "1rc2" splits into 1, rc, 2
+            if !current.is_empty() && is_digit != digits {
+                flush(&mut current, digits, &mut items);
+            }
+            digits = is_digit;
+            current.push(c);
+        }
+    }
+    flush(&mut current, digits, &mut items);
+
+    // Trailing zeros and release qualifiers don't matter: 1.0 == 1 == 1.0.0-ga
+    while let Some(last) = items.last() {
+        match last {
+            Item::Number(0) => items.pop(),
+            Item::Qualifier(q) if qualifier_rank(q) == RELEASE_RANK => items.pop(),
+            _ => break,
+        };
+    }
+    items
+}
+
+fn compare_items(a: Option<&Item>, b: Option<&Item>) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (Some(Item::Number(x)), Some(Item::Number(y))) => x.cmp(y),
+        (Some(Item::Number(x)), None) => x.cmp(&0),
+        (None, Some(Item::Number(y))) => 0.cmp(y),
+        // A number always beats a qualifier: 1.0.1 > 1.0-sp1 > 1.0 > 1.0-rc1
+        (Some(Item::Number(_)), Some(Item::Qualifier(_))) => Ordering::Greater,
+        (Some(Item::Qualifier(_)), Some(Item::Number(_))) => Ordering::Less,
+        (Some(Item::Qualifier(x)), None) => qualifier_rank(x).cmp(&RELEASE_RANK),
+        (None, Some(Item::Qualifier(y))) => RELEASE_RANK.cmp(&qualifier_rank(y)),
+        (Some(Item::Qualifier(x)), Some(Item::Qualifier(y))) => qualifier_rank(x)
+            .cmp(&qualifier_rank(y))
+            .then_with(|| x.cmp(y)),
+    }
+}
+
+/// Compare two Maven versions
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let a = tokenize(a);
+    let b = tokenize(b);
+    for i in 0..a.len().max(b.len()) {
+        let ordering = compare_items(a.get(i), b.get(i));
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// One interval of a version range; `None` bounds are unbounded
+#[derive(Debug, Clone, PartialEq)]
+struct Interval {
+    lower: Option<(String, bool)>,
+    upper: Option<(String, bool)>,
+}
+
+impl Interval {
+    fn contains(&self, version: &str) -> bool {
+        let above = match &self.lower {
+            Some((bound, inclusive)) => match compare(version, bound) {
+                Ordering::Greater => true,
+                Ordering::Equal => *inclusive,
+                Ordering::Less => false,
+            },
+            None => true,
+        };
+        let below = match &self.upper {
+            Some((bound, inclusive)) => match compare(version, bound) {
+                Ordering::Less => true,
+                Ordering::Equal => *inclusive,
+                Ordering::Greater => false,
+            },
+            None => true,
+        };
+        above && below
+    }
+}
+
+/// A Maven version range such as `[1.2,2.0)`, `(,3.0]`, `[1.5]` or `[1,2),[3,4)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionRange {
+    intervals: Vec<Interval>,
+}
+
+impl VersionRange {
+    /// Parse `spec`; plain versions like `1.0` are not ranges and give `Ok(None)`
+    pub fn parse(spec: &str) -> anyhow::Result<Option<Self>> {
+        let spec = spec.trim();
+        if !spec.starts_with('[') && !spec.starts_with('(') {
+            return Ok(None);
+        }
+
+        let invalid = || anyhow::anyhow!("Invalid version range '{}'", spec);
+        let mut intervals = Vec::new();
+        let mut rest = spec;
+
+        while !rest.is_empty() {
+            let lower_inclusive = match rest.chars().next() {
+                Some('[') => true,
+                Some('(') => false,
+                _ => return Err(invalid()),
+            };
+            let end = rest.find([']', ')']).ok_or_else(invalid)?;
+            let upper_inclusive = &rest[end..=end] == "]";
+            let body = &rest[1..end];
+
+            let interval = match body.split_once(',') {
+                Some((lower, upper)) => {
+                    let bound = |v: &str, inclusive| {
+                        let v = v.trim();
+                        (!v.is_empty()).then(|| (v.to_string(), inclusive))
+                    };
+                    Interval {
+                        lower: bound(lower, lower_inclusive),
+                        upper: bound(upper, upper_inclusive),
+                    }
+                }
+                // `[1.5]` means exactly 1.5
+                None if lower_inclusive && upper_inclusive && !body.trim().is_empty() => {
+                    let exact = Some((body.trim().to_string(), true));
+                    Interval {
+                        lower: exact.clone(),
+                        upper: exact,
+                    }
+                }
+                None => return Err(invalid()),
+            };
+            intervals.push(interval);
+
+            rest = rest[end + 1..].trim_start();
+            if let Some(next) = rest.strip_prefix(',') {
+                rest = next.trim_start();
+                if rest.is_empty() {
+                    return Err(invalid());
+                }
+            } else if !rest.is_empty() {
+                return Err(invalid());
+            }
+        }
+
+        Ok(Some(Self { intervals }))
+    }
+
+    pub fn contains(&self, version: &str) -> bool {
+        self.intervals.iter().any(|i| i.contains(version))
+    }
+
+    /// The versions of `available` inside this range, highest first
+    pub fn matching<'v>(&self, available: &'v [String]) -> Vec<&'v String> {
+        let mut matching: Vec<&String> = available.iter().filter(|v| self.contains(v)).collect();
+        matching.sort_by(|a, b| compare(b, a));
+        matching
+    }
+}
+
+/// Whether `spec` is a version range rather than a plain version
+pub fn is_range(spec: &str) -> bool {
+    matches!(VersionRange::parse(spec), Ok(Some(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_ordering() {
+        assert_eq!(compare("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare("2.0", "10.0"), Ordering::Less);
+        assert_eq!(compare("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare("1", "1.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_qualifier_ordering() {
+        let ordered = [
+            "1.0-alpha1",
+            "1.0-beta2",
+            "1.0-M3",
+            "1.0-RC1",
+            "1.0-SNAPSHOT",
+            "1.0",
+            "1.0-sp1",
+            "1.0.1",
+        ];
+        for pair in ordered.windows(2) {
+            assert_eq!(compare(pair[0], pair[1]), Ordering::Less, "{:?}", pair);
+        }
+        assert_eq!(compare("1.0-final", "1.0"), Ordering::Equal);
+        assert_eq!(compare("1.0-rc2", "1.0-rc10"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_guava_style_versions() {
+        assert_eq!(compare("33.0.0-jre", "32.1.3-jre"), Ordering::Greater);
+        assert_eq!(compare("31.1-jre", "31.1-android"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_plain_version_is_not_a_range() {
+        assert_eq!(VersionRange::parse("1.2.3").unwrap(), None);
+        assert!(!is_range("1.2.3"));
+        assert!(is_range("[1.0,2.0)"));
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let range = VersionRange::parse("[1.2,2.0)").unwrap().unwrap();
+        assert!(range.contains("1.2"));
+        assert!(range.contains("1.9.9"));
+        assert!(!range.contains("2.0"));
+        assert!(!range.contains("1.1"));
+
+        let range = VersionRange::parse("(,3.0]").unwrap().unwrap();
+        assert!(range.contains("0.1"));
+        assert!(range.contains("3.0"));
+        assert!(!range.contains("3.0.1"));
+
+        let range = VersionRange::parse("[1.5]").unwrap().unwrap();
+        assert!(range.contains("1.5"));
+        assert!(!range.contains("1.5.1"));
+    }
+
+    #[test]
+    fn test_range_union_and_matching() {
+        let range = VersionRange::parse("[1,2), [3,4)").unwrap().unwrap();
+        let available: Vec<String> = ["1.0", "1.5", "2.0", "3.1", "4.0"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(range.matching(&available), vec!["3.1", "1.5", "1.0"]);
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        for spec in ["[1.0", "[]", "[1,2),", "(1.5)", "[1,2)x"] {
+            assert!(VersionRange::parse(spec).is_err(), "{}", spec);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::fs;
+use std::path::PathBuf;
 use tempfile::TempDir;
 
 /// Helper to create a jpkg command
@@ -9,6 +10,26 @@ fn jpkg_cmd() -> Command {
     Command::cargo_bin("jpkg").unwrap()
 }
 
+/// Helper to init `testproject` in `temp_dir` with `entries` in place of its
+/// empty dependencies; returns the project directory
+fn init_project(temp_dir: &TempDir, entries: &str) -> PathBuf {
+    jpkg_cmd()
+        .current_dir(temp_dir.path())
+        .arg("init")
+        .arg("testproject")
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("testproject");
+    let manifest = fs::read_to_string(project_dir.join("jpkg.json")).unwrap();
+    fs::write(
+        project_dir.join("jpkg.json"),
+        manifest.replace(r#""dependencies": {}"#, entries),
+    )
+    .unwrap();
+    project_dir
+}
+
 #[test]
 fn test_version_command() {
     jpkg_cmd().arg("version").assert().success();
@@ -213,6 +234,100 @@ fn test_install_creates_lockfile() {
     );
 }
 
+#[test]
+fn test_frozen_install_requires_lockfile() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_dir = init_project(&temp_dir, r#""dependencies": {}"#);
+
+    jpkg_cmd()
+        .current_dir(&project_dir)
+        .arg("install")
+        .arg("--frozen")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("jpkg.lock not found"));
+    assert!(!project_dir.join("jpkg.lock").exists());
+}
+
+#[test]
+fn test_offline_install_needs_cached_poms() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_dir = init_project(
+        &temp_dir,
+        r#""dependencies": {"com.google.guava:guava": "31.1-jre"}"#,
+    );
+
+    // An empty cache: resolving must fail rather than drop guava's dependencies
+    jpkg_cmd()
+        .current_dir(&project_dir)
+        .env("HOME", temp_dir.path())
+        .arg("install")
+        .arg("--offline")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "POM of com.google.guava:guava:31.1-jre is not in the cache (offline mode)",
+        ));
+    assert!(!project_dir.join("jpkg.lock").exists());
+}
+
+#[test]
+fn test_dependency_without_pom() {
+    let temp_dir = TempDir::new().unwrap();
+    let project_dir = init_project(
+        &temp_dir,
+        r#""dependencies": {"com.example:legacy": {"version": "1.0", "pom": false}}"#,
+    );
+
+    // Resolution doesn't ask for the POM; only the jar is missing
+    jpkg_cmd()
+        .current_dir(&project_dir)
+        .env("HOME", temp_dir.path())
+        .arg("install")
+        .arg("--offline")
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("com.example:legacy:1.0"))
+        .stderr(predicate::str::contains(
+            "Artifact com.example:legacy:1.0 not in cache (offline mode)",
+        ));
+}
+
+#[test]
+fn test_install_from_directory_repository() {
+    let temp_dir = TempDir::new().unwrap();
+
+    // Naming the fixture repository "central" keeps Maven Central out of it
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/repo");
+    let project_dir = init_project(
+        &temp_dir,
+        &format!(
+            r#""dependencies": {{"com.example:core": "1.0", "com.example:log": "[1.0,3.0)"}},
+            "repositories": [{{"name": "central", "url": {}}}]"#,
+            serde_json::to_string(&fixtures).unwrap()
+        ),
+    );
+
+    jpkg_cmd()
+        .current_dir(&project_dir)
+        .env("HOME", temp_dir.path())
+        .arg("install")
+        .assert()
+        .success();
+
+    // util's range picks the highest published version. log has no maven-metadata.xml,
+    // so its range is matched against its version directories.
+    let lib_dir = project_dir.join("lib");
+    for jar in ["core-1.0.jar", "util-1.1.jar", "log-2.0.jar"] {
+        assert!(lib_dir.join(jar).exists(), "{} should be installed", jar);
+    }
+    assert!(!lib_dir.join("util-1.0.jar").exists());
+    let lock = fs::read_to_string(project_dir.join("jpkg.lock")).unwrap();
+    assert!(lock.contains("com.example:util:1.1"));
+    assert!(lock.contains(r#""repository-checksum": "sha1:"#));
+    assert!(!lock.contains("testkit"));
+}
+
 #[test]
 fn test_log_command() {
     jpkg_cmd().arg("log").assert().success();